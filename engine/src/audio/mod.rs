@@ -5,14 +5,54 @@ use sdl2_mixer;
 use sdl2::AudioSubsystem;
 use sdl2_mixer::{INIT_MP3, INIT_FLAC, INIT_MOD, INIT_FLUIDSYNTH, INIT_MODPLUG, INIT_OGG, AUDIO_S16LSB, Music, Sdl2MixerContext};
 
+use util::{self, HashSet};
+
+pub trait AudioBackend {
+    fn load(&mut self, id: usize, path: &Path);
+    fn play(&self, id: &usize);
+    fn stop(&self);
+}
+
 pub struct Audio {
+    backend: Box<AudioBackend>,
+}
+
+impl Audio {
+    pub fn new(backend: Box<AudioBackend>) -> Audio {
+        Audio {
+            backend: backend,
+        }
+    }
+
+    pub fn sdl() -> Audio {
+        Audio::new(Box::new(SdlAudio::new()))
+    }
+
+    pub fn headless() -> Audio {
+        Audio::new(Box::new(NullAudio::new()))
+    }
+
+    pub fn load(&mut self, id: usize, path: &Path) {
+        self.backend.load(id, path);
+    }
+
+    pub fn play(&self, id: &usize) {
+        self.backend.play(id);
+    }
+
+    pub fn stop(&self) {
+        self.backend.stop();
+    }
+}
+
+pub struct SdlAudio {
     sounds: HashMap<usize, Music>,
     mixer: Sdl2MixerContext,
     subsystem: AudioSubsystem,
 }
 
-impl Audio {
-    pub fn new() -> Audio {
+impl SdlAudio {
+    pub fn new() -> SdlAudio {
         let sdl = sdl2::init().unwrap();
         let audio = sdl.audio().unwrap();
         let mixer_context = sdl2_mixer::init(INIT_MP3 | INIT_FLAC | INIT_MOD | INIT_FLUIDSYNTH | INIT_MODPLUG | INIT_OGG).unwrap();
@@ -22,32 +62,61 @@ impl Audio {
         let chunk_size = 1024;
         let _ = sdl2_mixer::open_audio(freq, format, channels, chunk_size).unwrap();
         sdl2_mixer::allocate_channels(0);
-        Audio {
+        SdlAudio {
             sounds: Default::default(),
             mixer: mixer_context,
             subsystem: audio,
         }
     }
+}
 
-    pub fn load(&mut self, id: usize, path: &Path) {
+impl AudioBackend for SdlAudio {
+    fn load(&mut self, id: usize, path: &Path) {
         let music = sdl2_mixer::Music::from_file(path).unwrap();
         self.sounds.insert(id, music);
     }
 
-    pub fn play(&self, id: &usize) {
+    fn play(&self, id: &usize) {
         match self.sounds.get(id) {
             Some(music) => { music.play(1).unwrap(); }
             None => { println!("Tried to play invalid audio with id: {}", id); }
         }
     }
 
-    pub fn stop(&self) {
+    fn stop(&self) {
         sdl2_mixer::Music::halt();
     }
 }
 
-impl Drop for Audio {
+impl Drop for SdlAudio {
     fn drop(&mut self) {
         sdl2_mixer::close_audio();
     }
 }
+
+/// Backend which keeps track of loaded ids but never touches a sound device
+pub struct NullAudio {
+    sounds: HashSet<usize>,
+}
+
+impl NullAudio {
+    pub fn new() -> NullAudio {
+        NullAudio {
+            sounds: util::hashset(),
+        }
+    }
+}
+
+impl AudioBackend for NullAudio {
+    fn load(&mut self, id: usize, _path: &Path) {
+        self.sounds.insert(id);
+    }
+
+    fn play(&self, id: &usize) {
+        if !self.sounds.contains(id) {
+            println!("Tried to play invalid audio with id: {}", id);
+        }
+    }
+
+    fn stop(&self) { }
+}
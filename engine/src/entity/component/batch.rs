@@ -0,0 +1,92 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use graphics::{Graphics, SpriteAttrs};
+
+/// Many instances of one sprite drawn by a single entity, e.g. all bullets of a kind.
+/// Instance slots are reserved as the batch grows and are reused afterwards
+pub struct SpriteBatch {
+    sprite: usize,
+    ids: Vec<usize>,
+    /// Slots handed out through `acquire`
+    acquired: usize,
+    attrs: Vec<SpriteAttrs>,
+    graphics: Rc<RefCell<Graphics>>,
+}
+
+impl SpriteBatch {
+    pub fn new(graphics: Rc<RefCell<Graphics>>, sprite: usize) -> SpriteBatch {
+        SpriteBatch {
+            sprite: sprite,
+            ids: Vec::new(),
+            acquired: 0,
+            attrs: Vec::new(),
+            graphics: graphics,
+        }
+    }
+
+    pub fn sprite(&self) -> usize {
+        self.sprite
+    }
+
+    /// Reserves a slot for one more instance, false once the sprite has none left
+    pub fn acquire(&mut self) -> bool {
+        if self.acquired == self.ids.len() {
+            match self.graphics.borrow_mut().get_id(&self.sprite) {
+                Some(id) => self.ids.push(id),
+                None => return false,
+            }
+        }
+        self.acquired += 1;
+        true
+    }
+
+    /// Gives back a slot reserved with `acquire`, it is kept for the next instance
+    pub fn release(&mut self) {
+        self.acquired = self.acquired.saturating_sub(1);
+    }
+
+    /// Starts a new frame of instances
+    pub fn clear(&mut self) {
+        self.attrs.clear();
+    }
+
+    pub fn push(&mut self, x: f32, y: f32, rot: f32) {
+        let mut attrs = SpriteAttrs::default();
+        attrs.set_rot(rot);
+        attrs.set_pos(x, y);
+        self.attrs.push(attrs);
+    }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    /// Writes every instance pushed since the last clear, unused slots are hidden.
+    /// Instances past the acquired slots are dropped if the sprite runs out
+    pub fn render(&mut self) {
+        let mut graphics = self.graphics.borrow_mut();
+        while self.ids.len() < self.attrs.len() {
+            match graphics.get_id(&self.sprite) {
+                Some(id) => self.ids.push(id),
+                None => break,
+            }
+        }
+        graphics.set_sprite_attrs(&self.sprite, &self.ids, &self.attrs);
+        if self.ids.len() > self.attrs.len() {
+            let hidden = vec![SpriteAttrs::hidden(); self.ids.len() - self.attrs.len()];
+            graphics.set_sprite_attrs(&self.sprite, &self.ids[self.attrs.len()..], &hidden);
+        }
+    }
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        let mut graphics = self.graphics.borrow_mut();
+        let hidden = vec![SpriteAttrs::hidden(); self.ids.len()];
+        graphics.set_sprite_attrs(&self.sprite, &self.ids, &hidden);
+        for id in self.ids.drain(..) {
+            graphics.return_id(&self.sprite, id);
+        }
+    }
+}
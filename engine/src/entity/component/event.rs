@@ -1,11 +1,14 @@
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::any::Any;
 
 use Engine;
 use entity::Entity;
-use event::{Event, Handler, SysEvent};
+use event::{Event, CustomEvent, Handler, SysEvent};
+use scene::Handle;
 
-struct Timer {
+#[derive(Clone)]
+pub struct Timer {
     id: usize,
     repeat: bool,
     amount: f32,
@@ -38,13 +41,13 @@ impl Timer {
 }
 
 pub struct EventComp<E: Entity> {
-    id: usize,
+    id: Handle,
     handler: Rc<RefCell<Handler<E>>>,
     timers: Vec<Timer>,
 }
 
 impl<E: Entity> EventComp<E> {
-    pub fn new(id: usize, handler: Rc<RefCell<Handler<E>>>) -> EventComp<E> {
+    pub fn new(id: Handle, handler: Rc<RefCell<Handler<E>>>) -> EventComp<E> {
         EventComp {
             id: id,
             handler: handler,
@@ -67,6 +70,15 @@ impl<E: Entity> EventComp<E> {
         }
     }
 
+    /// Copies of the running timers, used when snapshotting the owning entity
+    pub fn timers(&self) -> Vec<Timer> {
+        self.timers.clone()
+    }
+
+    pub fn set_timers(&mut self, timers: Vec<Timer>) {
+        self.timers = timers;
+    }
+
     pub fn set_timer(&mut self, id: usize, amount: f32) {
         self.set_timer_manual(id, amount, false, Event::Timer(id), 0);
     }
@@ -113,15 +125,62 @@ impl<E: Entity> EventComp<E> {
         self.handler.borrow_mut().unsubscribe(self.id.clone(), event);
     }
 
+    /// Subscribes to broadcasts of custom events carrying a `T`
+    pub fn subscribe_custom<T: Any>(&self) {
+        self.subscribe(Event::Custom(CustomEvent::channel::<T>()));
+    }
+
+    pub fn unsubscribe_custom<T: Any>(&self) {
+        self.unsubscribe(Event::Custom(CustomEvent::channel::<T>()));
+    }
+
+    /// Entities with a lower priority are dispatched events first, the default is 0
+    pub fn set_priority(&self, priority: i32) {
+        self.handler.borrow_mut().set_priority(self.id, priority);
+    }
+
+    pub fn pause(&self) {
+        self.handler.borrow_mut().time.pause();
+    }
+
+    pub fn resume(&self) {
+        self.handler.borrow_mut().time.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.handler.borrow().time.is_paused()
+    }
+
+    /// Advances a paused simulation by a single step
+    pub fn step(&self) {
+        self.handler.borrow_mut().time.step();
+    }
+
+    pub fn set_time_scale(&self, scale: f32) {
+        self.handler.borrow_mut().time.set_scale(scale);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.handler.borrow().time.scale()
+    }
+
     pub fn destroy_self(&self) {
         self.handler.borrow_mut().enqueue_sys(SysEvent::Destroy(self.id));
     }
 
-    pub fn fast_forward(&self, t: f32) {
-        self.handler.borrow_mut().enqueue_sys(SysEvent::FastForward(t));
+    pub fn seek(&self, timeline: String, time: f32) {
+        self.handler.borrow_mut().enqueue_sys(SysEvent::Seek(timeline, time));
     }
 
-    pub fn destroy_other(&self, id: usize) {
+    pub fn checkpoint(&self, time: f32) {
+        self.handler.borrow_mut().enqueue_sys(SysEvent::Checkpoint(time));
+    }
+
+    pub fn start_session(&self, level: String, start_time: f32) {
+        self.handler.borrow_mut().enqueue_sys(SysEvent::StartSession(level, start_time));
+    }
+
+    pub fn destroy_other(&self, id: Handle) {
         self.handler.borrow_mut().enqueue_sys(SysEvent::Destroy(id));
     }
 
@@ -129,10 +188,19 @@ impl<E: Entity> EventComp<E> {
         self.handler.borrow_mut().enqueue_all(event);
     }
 
-    pub fn dispatch_to(&self, id: usize, event: Event) {
+    pub fn dispatch_to(&self, id: Handle, event: Event) {
         self.handler.borrow_mut().enqueue_specific(id, event);
     }
 
+    /// Sends `event` to every entity subscribed to custom events of its type
+    pub fn dispatch_custom<T: Any>(&self, event: T) {
+        self.dispatch(Event::Custom(CustomEvent::new(event)));
+    }
+
+    pub fn dispatch_custom_to<T: Any>(&self, id: Handle, event: T) {
+        self.dispatch_to(id, Event::Custom(CustomEvent::new(event)));
+    }
+
     pub fn create_entity(&self, f: Box<Fn(&Engine<E>) -> E>) {
         self.handler.borrow_mut().enqueue_sys(SysEvent::Create(f));
     }
@@ -7,6 +7,8 @@ use graphics::{Graphics, SpriteAttrs};
 pub struct GraphicsComp {
     sprite: usize,
     id: usize,
+    rot: f32,
+    scale: (f32, f32),
     data: SpriteAttrs,
     graphics: Rc<RefCell<Graphics>>
 }
@@ -19,6 +21,8 @@ impl GraphicsComp {
             data: Default::default(),
             graphics: graphics,
             id: id,
+            rot: 0.0,
+            scale: (1.0, 1.0),
         }
     }
 
@@ -27,7 +31,22 @@ impl GraphicsComp {
     }
 
     pub fn set_rot(&mut self, t: f32) {
-        self.data.set_rot(t);
+        self.rot = t;
+        self.data.set_rot_scale(t, self.scale.0, self.scale.1);
+    }
+
+    /// Stretches the sprite along its own axes, applied before rotation
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.scale = (sx, sy);
+        self.data.set_rot_scale(self.rot, sx, sy);
+    }
+
+    pub fn get_scale(&self) -> (f32, f32) {
+        self.scale
+    }
+
+    pub fn get_rot(&self) -> f32 {
+        self.rot
     }
 
     pub fn set_pos(&mut self, x: f32, y: f32) {
@@ -45,6 +64,11 @@ impl GraphicsComp {
     pub fn render(&self) {
         self.graphics.borrow_mut().set_sprite_attr(&self.sprite, self.id, &self.data);
     }
+
+    /// Keeps the sprite off screen for this frame without moving it
+    pub fn hide(&self) {
+        self.graphics.borrow_mut().set_sprite_attr(&self.sprite, self.id, &SpriteAttrs::hidden());
+    }
 }
 
 impl Drop for GraphicsComp {
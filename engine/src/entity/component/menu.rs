@@ -13,7 +13,7 @@ use graphics::{Graphics};
 
 pub struct MenuComp {
     pub imgui: ImGui,
-    renderer: Renderer,
+    renderer: Option<Renderer>,
     graphics: Rc<RefCell<Graphics>>,
     prev_time: u64,
     scaler: f32,
@@ -24,13 +24,13 @@ pub struct MenuComp {
 pub struct MenuRenderer<'a, S: 'a> {
     pub state: &'a mut S,
     frame: Option<Ui<'a>>,
-    renderer: &'a mut Renderer,
+    renderer: Option<&'a mut Renderer>,
     graphics: Rc<RefCell<Graphics>>,
 }
 
 impl <'a, S> MenuRenderer<'a, S> {
-    pub fn render(mut self, ui: Ui<'a>) {
-        self.graphics.borrow_mut().render_menu(&mut self.renderer, ui);
+    pub fn render(self, ui: Ui<'a>) {
+        self.graphics.borrow_mut().render_menu(self.renderer, ui);
     }
 
     pub fn frame(&mut self) -> Ui<'a> {
@@ -89,7 +89,7 @@ impl<'comp> MenuComp {
 
         MenuRenderer {
             frame: Some(ui),
-            renderer: &mut self.renderer,
+            renderer: self.renderer.as_mut(),
             graphics: self.graphics.clone(),
             state: state,
         }
@@ -103,6 +103,6 @@ impl<'comp> MenuComp {
             self.graphics.borrow().get_menu_frame(&mut self.imgui, delta_t)
         };
         run_ui(&ui);
-        self.graphics.borrow_mut().render_menu(&mut self.renderer, ui);
+        self.graphics.borrow_mut().render_menu(self.renderer.as_mut(), ui);
     }
 }
@@ -16,6 +16,9 @@ pub use self::physics::*;
 mod graphics;
 pub use self::graphics::*;
 
+mod batch;
+pub use self::batch::*;
+
 mod audio;
 pub use self::audio::*;
 
@@ -24,3 +27,6 @@ pub use self::pg::*;
 
 mod menu;
 pub use self::menu::*;
+
+mod resource;
+pub use self::resource::*;
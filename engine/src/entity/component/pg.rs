@@ -1,7 +1,8 @@
 use std::rc::Rc;
+use std::f32;
 use nalgebra::{Vector2};
 
-use scene::{PhysicsWorld};
+use scene::{CullPolicy, PhysicsWorld};
 use entity::component::{PhysicsComp, GraphicsComp};
 
 /// Combined physics and graphics component,
@@ -15,6 +16,7 @@ pub struct PGComp {
     pub scaler: f32,
     screen_locked: bool,
     half_widths: (f32, f32),
+    oriented: bool,
 }
 
 impl PGComp {
@@ -31,6 +33,7 @@ impl PGComp {
                 world: world,
                 screen_locked: false,
                 half_widths: (0.0, 0.0),
+                oriented: false,
             }
         }
 
@@ -39,10 +42,52 @@ impl PGComp {
         self.half_widths = (half_widths.0 / self.scaler, half_widths.1 / self.scaler);
     }
 
+    /// Keeps the sprite and hitboxes rotated towards the direction of travel,
+    /// sprites are assumed to point up when unrotated
+    pub fn orient_to_velocity(&mut self, oriented: bool) {
+        self.oriented = oriented;
+        self.orient();
+    }
+
+    /// Rotates the sprite and all hitboxes around their positions
+    pub fn set_rot(&mut self, rot: f32) {
+        self.graphics.set_rot(rot);
+        for comp in self.physics.iter_mut() {
+            comp.set_rot(rot);
+        }
+    }
+
+    pub fn get_rot(&self) -> f32 {
+        self.graphics.get_rot()
+    }
+
+    fn orient(&mut self) {
+        if self.oriented && (self.velocity.x != 0.0 || self.velocity.y != 0.0) {
+            let rot = self.velocity.y.atan2(self.velocity.x) - f32::consts::FRAC_PI_2;
+            self.set_rot(rot);
+        }
+    }
+
+    /// Applies the cull policy to every hitbox of the component
+    pub fn set_cull(&self, policy: CullPolicy) {
+        for comp in self.physics.iter() {
+            comp.set_cull(policy);
+        }
+    }
+
     pub fn render(&self) {
         self.graphics.render();
     }
 
+    pub fn hide(&self) {
+        self.graphics.hide();
+    }
+
+    /// Scales the sprite only, hitboxes keep their size
+    pub fn set_scale(&mut self, sx: f32, sy: f32) {
+        self.graphics.set_scale(sx, sy);
+    }
+
     pub fn translate_gfx(&mut self, delta: Vector2<f32>) {
         self.graphics.translate(delta.x / self.scaler, delta.y / self.scaler);
     }
@@ -76,6 +121,17 @@ impl PGComp {
         self.translate(delta);
     }
 
+    /// Same as `set_pos`, but the hitboxes don't hit anything on the way
+    pub fn teleport(&mut self, pos: (f32, f32)) {
+        let (old_x, old_y) = self.get_pos();
+        let delta = Vector2::new(pos.0 - old_x, pos.1 - old_y);
+        self.graphics.translate(delta.x / self.scaler, delta.y / self.scaler);
+        for comp in self.physics.iter_mut() {
+            let target = comp.pos + delta;
+            comp.teleport(target);
+        }
+    }
+
     pub fn set_pos_gfx(&mut self, pos: (f32, f32)) {
         let converted_pos = (pos.0 * self.scaler, pos.1 * self.scaler);
         self.set_pos(converted_pos);
@@ -102,6 +158,7 @@ impl PGComp {
         self.velocity += self.acceleration * dt;
         let delta = self.velocity * dt;
         self.translate(delta);
+        self.orient();
 
         if self.screen_locked {
             let new_pos = self.get_gfx_pos();
@@ -3,33 +3,48 @@ use ncollide_geometry::shape::ShapeHandle2;
 use std::rc::Rc;
 
 use entity::Entity;
-use scene::{Scene, PhysicsWorld};
+use scene::{CullPolicy, Scene, PhysicsWorld, Handle};
 
 pub struct PhysicsComp {
-    pub id: usize,
+    pub id: Handle,
+    entity_id: Handle,
     pub velocity: Vector2<f32>,
     pub acceleration: Vector2<f32>,
     pub pos: Vector2<f32>,
+    pub rot: f32,
     pub world: Rc<PhysicsWorld>,
 }
 
 impl PhysicsComp {
-    pub fn new<E: Entity>(entity_id: usize,
+    pub fn new<E: Entity>(entity_id: Handle,
                           tag: usize,
                           position: Vector2<f32>,
                           shape: ShapeHandle2<f32>,
                           group: u8,
                           scene: &Scene<E>)
                           -> PhysicsComp {
-        let id = scene.physics.add(position.clone(),
-                                           shape,
-                                           Rc::new(PhysicsData::new(entity_id, tag, group)));
+        PhysicsComp::in_world(entity_id, tag, position, shape, group, scene.physics.clone())
+    }
+
+    /// Same as `new`, for entities adding hitboxes after they were created
+    pub fn in_world(entity_id: Handle,
+                    tag: usize,
+                    position: Vector2<f32>,
+                    shape: ShapeHandle2<f32>,
+                    group: u8,
+                    world: Rc<PhysicsWorld>)
+                    -> PhysicsComp {
+        let id = world.add(position.clone(),
+                           shape,
+                           Rc::new(PhysicsData::new(entity_id, tag, group)));
         PhysicsComp {
             id: id,
+            entity_id: entity_id,
             pos: position,
+            rot: 0.0,
             velocity: Vector2::new(0.0, 0.0),
             acceleration: Vector2::new(0.0, 0.0),
-            world: scene.physics.clone(),
+            world: world,
         }
     }
 
@@ -48,6 +63,7 @@ impl PhysicsComp {
 
     pub fn sync_pos(&self) {
         self.world.set_pos(&self.id, self.pos);
+        self.world.set_rot(&self.id, self.rot);
     }
 
     pub fn set_pos(&mut self, pos: Vector2<f32>) {
@@ -55,6 +71,30 @@ impl PhysicsComp {
         self.world.set_pos(&self.id, pos);
     }
 
+    /// Moves the hitbox without hitting anything in between
+    pub fn teleport(&mut self, pos: Vector2<f32>) {
+        self.pos = pos;
+        self.world.teleport(&self.id, pos);
+    }
+
+    pub fn get_rot(&self) -> f32 {
+        self.rot
+    }
+
+    pub fn set_rot(&mut self, rot: f32) {
+        self.rot = rot;
+        self.world.set_rot(&self.id, rot);
+    }
+
+    /// Decides what happens to the entity once this hitbox leaves the playfield
+    pub fn set_cull(&self, policy: CullPolicy) {
+        self.world.set_cull(&self.id, self.entity_id, policy);
+    }
+
+    pub fn set_shape(&mut self, shape: ShapeHandle2<f32>) {
+        self.world.set_shape(&self.id, shape);
+    }
+
     pub fn update(&mut self, dt: f32) {
         self.velocity += self.acceleration * dt;
         let delta = self.velocity * dt;
@@ -70,13 +110,13 @@ impl Drop for PhysicsComp {
 
 #[derive(Clone, Default)]
 pub struct PhysicsData {
-    pub entity_id: usize,
+    pub entity_id: Handle,
     pub tag: usize,
     pub group: u8,
 }
 
 impl PhysicsData {
-    pub fn new(entity_id: usize, tag: usize, group: u8) -> PhysicsData {
+    pub fn new(entity_id: Handle, tag: usize, group: u8) -> PhysicsData {
         PhysicsData {
             entity_id: entity_id,
             tag: tag,
@@ -0,0 +1,40 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use Engine;
+use entity::Entity;
+use resource::Resources;
+
+/// Access to the engine's shared resources
+#[derive(Clone)]
+pub struct ResourceComp {
+    resources: Rc<RefCell<Resources>>,
+}
+
+impl ResourceComp {
+    pub fn new<E: Entity>(engine: &Engine<E>) -> ResourceComp {
+        ResourceComp {
+            resources: engine.resources.clone(),
+        }
+    }
+
+    pub fn set<T: Any>(&self, value: T) {
+        self.resources.borrow_mut().insert(value);
+    }
+
+    /// Copy of the resource of type `T`, if one has been stored
+    pub fn get<T: Any + Clone>(&self) -> Option<T> {
+        self.resources.borrow().get::<T>().cloned()
+    }
+
+    /// Copy of the resource of type `T`, or its default value if none has been stored yet
+    pub fn get_or_default<T: Any + Clone + Default>(&self) -> T {
+        self.get::<T>().unwrap_or_else(T::default)
+    }
+
+    /// Runs `f` on the resource of type `T`, which is created with its default value if missing
+    pub fn update<T: Any + Default, R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(self.resources.borrow_mut().entry::<T>())
+    }
+}
@@ -2,19 +2,19 @@ use std::rc::Rc;
 
 use Engine;
 use entity::Entity;
-use scene::{EntityAccessor, World};
+use scene::{EntityAccessor, World, Handle};
 use util::HashSet;
 
 pub struct WorldCompBuilder<E: Entity> {
     world : Rc<World<E>>,
     alias: Option<String>,
     tags: Vec<String>,
-    id: usize,
+    id: Handle,
 }
 
 impl<E: Entity> WorldCompBuilder<E> {
     pub fn new(engine: &Engine<E>) -> WorldCompBuilder<E> {
-        let id = engine.scene.world.registry.borrow_mut().get_id();
+        let id = engine.scene.world.registry.borrow_mut().get_handle();
         let world = engine.scene.world.clone();
         WorldCompBuilder {
             world: world,
@@ -24,7 +24,7 @@ impl<E: Entity> WorldCompBuilder<E> {
         }
     }
 
-    pub fn with_id(engine: &Engine<E>, id: usize) -> WorldCompBuilder<E> {
+    pub fn with_id(engine: &Engine<E>, id: Handle) -> WorldCompBuilder<E> {
         let world = engine.scene.world.clone();
         WorldCompBuilder {
             world: world,
@@ -68,7 +68,7 @@ impl<E: Entity> WorldCompBuilder<E> {
 }
 
 pub struct WorldComp<E: Entity> {
-    pub id: usize,
+    pub id: Handle,
     world: Rc<World<E>>,
     alias: Option<String>,
     tags: Vec<String>,
@@ -78,7 +78,7 @@ use std::clone::Clone;
 
 impl<E: Entity> Clone for WorldComp<E> {
     fn clone(&self) -> WorldComp<E> {
-        let id = self.world.registry.borrow_mut().get_id();
+        let id = self.world.registry.borrow_mut().get_handle();
         WorldComp {
             world: self.world.clone(),
             alias: self.alias.clone(),
@@ -90,39 +90,39 @@ impl<E: Entity> Clone for WorldComp<E> {
 
 
 impl<E: Entity> WorldComp<E> {
-    pub fn find_aliased_entity_id(&self, alias: &String) -> Option<usize> {
+    pub fn find_aliased_entity_id(&self, alias: &String) -> Option<Handle> {
         match self.world.registry.borrow_mut().get_aliased_id(alias) {
             Some(id) => Some(*id),
             None => None,
         }
     }
 
-    pub fn find_aliased_entity_alias(&self, id: &usize) -> Option<String> {
+    pub fn find_aliased_entity_alias(&self, id: &Handle) -> Option<String> {
         match self.world.registry.borrow_mut().get_aliased_string(id) {
             Some(s) => Some(s.clone()),
             None => None,
         }
     }
 
-    pub fn get_entity(&self, id: &usize) -> EntityAccessor<E> {
+    pub fn get_entity(&self, id: &Handle) -> EntityAccessor<E> {
         self.world.get_entity(id)
     }
 
-    pub fn get_tagged(&self, tag: &String)  -> Option<HashSet<usize>> {
+    pub fn get_tagged(&self, tag: &String)  -> Option<HashSet<Handle>> {
         match self.world.registry.borrow_mut().get_tagged(tag) {
             Some(tags) => Some(tags.clone()),
             None => None,
         }
     }
 
-    pub fn remove_tagged(&mut self, tag: &String)  -> Option<HashSet<usize>> {
+    pub fn remove_tagged(&mut self, tag: &String)  -> Option<HashSet<Handle>> {
         self.world.registry.borrow_mut().remove_tagged(tag).clone()
     }
 }
 
 impl<E: Entity> Drop for WorldComp<E> {
     fn drop(&mut self) {
-        self.world.registry.borrow_mut().return_id(self.id);
+        self.world.registry.borrow_mut().return_handle(self.id);
         for tag in self.tags.iter() {
             self.world.registry.borrow_mut().untag_id(&self.id, tag);
         }
@@ -3,10 +3,17 @@ pub mod component;
 use std::rc::Rc;
 use Engine;
 use event::Event;
+use scene::Handle;
 
 pub trait Entity {
     fn handle_event(&mut self, e: Rc<Event>);
-    fn id(&self) -> usize;
+    fn id(&self) -> Handle;
+
+    /// Captures the entity's state as a spawner which recreates it.
+    /// Entities returning None are left alone when a snapshot is restored
+    fn snapshot(&self) -> Option<Box<Fn(&Engine<Self>) -> Self>> where Self: Sized {
+        None
+    }
 }
 
 pub trait EntityBuilder<E: Entity> {
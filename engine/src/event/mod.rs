@@ -3,15 +3,19 @@ use std::cmp::PartialEq;
 use std::mem;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::any::Any;
+use std::any::{Any, TypeId};
 use glium::glutin::{VirtualKeyCode, MouseButton, MouseScrollDelta, TouchPhase};
 
 use Engine;
 use entity::Entity;
 use entity::component::PhysicsData;
-use util::{self, HashMap, HashSet};
+use physics::Contact;
+use scene::Handle;
+use time::TimeController;
+use std::collections::BTreeMap;
+use util::{self, HashMap};
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum InputState {
     Pressed,
     Released,
@@ -19,18 +23,64 @@ pub enum InputState {
 
 pub enum Event {
     Update(f32),
-    Collision(usize, CollisionData),
+    /// Sent on the first step two objects touch
+    CollisionBegin(Handle, CollisionData),
+    /// Sent on every following step the objects keep touching
+    Collision(Handle, CollisionData),
+    /// Sent once the objects no longer touch, with the last contact between them
+    CollisionEnd(Handle, CollisionData),
+    /// Another object passed within the proximity margin without touching
+    Proximity(Handle, CollisionData),
+    /// A hitbox with `CullPolicy::Notify` left the playfield
+    OutOfBounds,
     KeyInput(InputState, KeyCode),
     MouseMove((f32, f32)),
     MouseInput(InputState, MouseButton),
     MouseScroll(MouseScrollDelta, TouchPhase),
     Spawn,
+    /// Sent instead of Spawn to entities recreated from a snapshot
+    Restore,
+    /// Sent to an entity right before it is removed from the world
+    Despawn,
+    /// Broadcast to subscribers once the entity with the given handle has been removed
+    Destroyed(Handle),
     Timer(usize),
     CTimer(u8, usize),
     Render,
     RenderCustom,
     RenderMenu,
-    Custom(Box<Any>),
+    Custom(CustomEvent),
+}
+
+/// Game defined event, subscriptions to custom events are keyed by the payload type
+pub struct CustomEvent {
+    type_id: TypeId,
+    payload: Box<Any>,
+}
+
+impl CustomEvent {
+    pub fn new<T: Any>(payload: T) -> CustomEvent {
+        CustomEvent {
+            type_id: TypeId::of::<T>(),
+            payload: Box::new(payload),
+        }
+    }
+
+    /// Payload-less event used to subscribe to the channel of `T`
+    pub fn channel<T: Any>() -> CustomEvent {
+        CustomEvent {
+            type_id: TypeId::of::<T>(),
+            payload: Box::new(()),
+        }
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref::<T>()
+    }
 }
 
 use std::fmt;
@@ -39,12 +89,19 @@ impl fmt::Debug for Event {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Event::Update(_) => write!(f, "Update"),
+            Event::CollisionBegin(_, _) => write!(f, "Collision Begin"),
             Event::Collision(_, _) => write!(f, "Collision"),
+            Event::CollisionEnd(_, _) => write!(f, "Collision End"),
+            Event::Proximity(_, _) => write!(f, "Proximity"),
+            Event::OutOfBounds => write!(f, "Out Of Bounds"),
             Event::KeyInput(_, _) => write!(f, "Key Input"),
             Event::MouseMove(_) => write!(f, "Mouse Movement"),
             Event::MouseScroll(_, _) => write!(f, "Mouse Scroll"),
             Event::MouseInput(_, _) => write!(f, "Mouse Input"),
             Event::Spawn => write!(f, "Spawn"),
+            Event::Restore => write!(f, "Restore"),
+            Event::Despawn => write!(f, "Despawn"),
+            Event::Destroyed(_) => write!(f, "Destroyed"),
             Event::Timer(_) => write!(f, "Timer"),
             Event::CTimer(_, _) => write!(f, "CTimer"),
             Event::Render => write!(f, "Render"),
@@ -74,12 +131,19 @@ pub type KeyCode = VirtualKeyCode;
 pub struct CollisionData {
     pub this_object: Rc<PhysicsData>,
     pub other_object: Rc<PhysicsData>,
+    pub contact: Contact,
 }
 
 pub enum SysEvent<E: Entity> {
-    Destroy(usize),
+    Destroy(Handle),
     Create(Box<Fn(&Engine<E>) -> E>),
-    FastForward(f32),
+    /// Brings the freshly started timeline (e.g. a level file) to the given time
+    Seek(String, f32),
+    /// Asks for a snapshot of the current timeline at the given time, only taken while seeking
+    Checkpoint(f32),
+    /// Marks the start of a replayable session with the level file and start time.
+    /// Starts playback if a replay is loaded, otherwise starts recording
+    StartSession(String, f32),
 }
 
 impl Hash for Event {
@@ -93,11 +157,21 @@ impl Hash for Event {
             Event::Spawn => state.write_u8(5),
             Event::Timer(_) => state.write_u8(7),
             Event::Render => state.write_u8(8),
-            Event::Custom(_) => state.write_u8(9),
+            Event::Custom(ref c) => {
+                state.write_u8(9);
+                c.type_id.hash(state);
+            }
             Event::CTimer(_, _) => state.write_u8(10),
             Event::RenderCustom => state.write_u8(11),
             Event::RenderMenu => state.write_u8(12),
             Event::MouseScroll(_, _) => state.write_u8(13),
+            Event::Restore => state.write_u8(14),
+            Event::Despawn => state.write_u8(15),
+            Event::Destroyed(_) => state.write_u8(16),
+            Event::Proximity(_, _) => state.write_u8(17),
+            Event::CollisionBegin(_, _) => state.write_u8(18),
+            Event::CollisionEnd(_, _) => state.write_u8(19),
+            Event::OutOfBounds => state.write_u8(20),
         }
     }
 }
@@ -108,17 +182,24 @@ impl PartialEq for Event {
     fn eq(&self, other: &Event) -> bool {
         match (self, other) {
             (&Event::Update(_), &Event::Update(_)) => true,
+            (&Event::CollisionBegin(_, _), &Event::CollisionBegin(_, _)) => true,
             (&Event::Collision(_, _), &Event::Collision(_, _)) => true,
+            (&Event::CollisionEnd(_, _), &Event::CollisionEnd(_, _)) => true,
+            (&Event::Proximity(_, _), &Event::Proximity(_, _)) => true,
+            (&Event::OutOfBounds, &Event::OutOfBounds) => true,
             (&Event::KeyInput(_, _), &Event::KeyInput(_, _)) => true,
             (&Event::MouseMove(_), &Event::MouseMove(_)) => true,
             (&Event::MouseInput(_, _), &Event::MouseInput(_, _)) => true,
             (&Event::MouseScroll(_, _), &Event::MouseScroll(_, _)) => true,
             (&Event::Spawn, &Event::Spawn) => true,
+            (&Event::Restore, &Event::Restore) => true,
+            (&Event::Despawn, &Event::Despawn) => true,
+            (&Event::Destroyed(_), &Event::Destroyed(_)) => true,
             (&Event::Timer(_), &Event::Timer(_)) => true,
             (&Event::Render, &Event::Render) => true,
             (&Event::RenderCustom, &Event::RenderCustom) => true,
             (&Event::RenderMenu, &Event::RenderMenu) => true,
-            (&Event::Custom(_), &Event::Custom(_)) => true,
+            (&Event::Custom(ref a), &Event::Custom(ref b)) => a.type_id == b.type_id,
             (&Event::CTimer(_, _), &Event::CTimer(_, _)) => true,
             _ => false,
         }
@@ -126,20 +207,31 @@ impl PartialEq for Event {
 }
 
 pub struct Dispatcher {
-    pub queue: Rc<RefCell<Vec<(usize, Rc<Event>)>>>,
+    pub queue: Rc<RefCell<Vec<(Handle, Rc<Event>)>>>,
 }
 
 impl Dispatcher {
-    pub fn dispatch(&self, id: usize, e: Event) {
+    pub fn dispatch(&self, id: Handle, e: Event) {
         self.queue.borrow_mut().push((id, Rc::new(e)));
     }
 }
 
+/// Position of an entity in the dispatch order.
+/// Entities with a lower priority receive events first, ties are broken by spawn order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DispatchKey {
+    pub priority: i32,
+    pub seq: u64,
+}
+
 #[derive(Default)]
 pub struct Handler<E: Entity> {
-    subscriptions: HashMap<Event, HashSet<usize>>,
-    pub queue: Rc<RefCell<Vec<(usize, Rc<Event>)>>>,
+    subscriptions: HashMap<Event, BTreeMap<DispatchKey, Handle>>,
+    order: HashMap<Handle, DispatchKey>,
+    next_seq: u64,
+    pub queue: Rc<RefCell<Vec<(Handle, Rc<Event>)>>>,
     sysqueue: Vec<SysEvent<E>>,
+    pub time: TimeController,
 }
 
 impl<E: Entity> Handler<E> {
@@ -148,31 +240,76 @@ impl<E: Entity> Handler<E> {
             sysqueue: vec![],
             queue: Rc::new(RefCell::new(vec![])),
             subscriptions: Default::default(),
+            order: util::hashmap(),
+            next_seq: 0,
+            time: TimeController::new(),
+        }
+    }
+
+    /// Assigns the entity its place in the dispatch order if it doesn't have one yet
+    pub fn register(&mut self, id: Handle) -> DispatchKey {
+        if let Some(key) = self.order.get(&id) {
+            return *key;
         }
+        let key = DispatchKey {
+            priority: 0,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        self.order.insert(id, key);
+        key
     }
 
-    pub fn subscribe(&mut self, id: usize, event: Event) {
+    pub fn dispatch_key(&self, id: &Handle) -> Option<DispatchKey> {
+        self.order.get(id).cloned()
+    }
+
+    pub fn set_priority(&mut self, id: Handle, priority: i32) {
+        let old = self.register(id);
+        let new = DispatchKey {
+            priority: priority,
+            seq: old.seq,
+        };
+        self.order.insert(id, new);
+        for (_, subs) in self.subscriptions.iter_mut() {
+            if let Some(sub) = subs.remove(&old) {
+                subs.insert(new, sub);
+            }
+        }
+    }
+
+    pub fn subscribe(&mut self, id: Handle, event: Event) {
+        let key = self.register(id);
         if !self.subscriptions.contains_key(&event) {
-            let mut set: HashSet<usize> = util::hashset();
-            set.insert(id);
-            self.subscriptions.insert(event, set);
+            let mut subs = BTreeMap::new();
+            subs.insert(key, id);
+            self.subscriptions.insert(event, subs);
         } else {
-            self.subscriptions.get_mut(&event).unwrap().insert(id);
+            self.subscriptions.get_mut(&event).unwrap().insert(key, id);
         }
     }
 
-    pub fn unsubscribe(&mut self, id: usize, event: Event) {
+    pub fn unsubscribe(&mut self, id: Handle, event: Event) {
+        let key = match self.order.get(&id) {
+            Some(key) => *key,
+            None => return,
+        };
         match self.subscriptions.get_mut(&event) {
             Some(subscribers) => {
-                subscribers.remove(&id);
+                subscribers.remove(&key);
             }
             None => {}
         }
     }
 
-    pub fn unsubscribe_all(&mut self, id: usize) {
+    /// Removes every subscription of the entity along with its place in the dispatch order
+    pub fn unsubscribe_all(&mut self, id: Handle) {
+        let key = match self.order.remove(&id) {
+            Some(key) => key,
+            None => return,
+        };
         for (_, subs) in self.subscriptions.iter_mut() {
-            subs.remove(&id);
+            subs.remove(&key);
         }
     }
 
@@ -180,7 +317,7 @@ impl<E: Entity> Handler<E> {
         match self.subscriptions.get(&event) {
             Some(subscribers) => {
                 let rc = Rc::new(event);
-                for sub in subscribers {
+                for sub in subscribers.values() {
                     self.queue.borrow_mut().push((sub.clone(), rc.clone()));
                 }
             }
@@ -188,11 +325,11 @@ impl<E: Entity> Handler<E> {
         }
     }
 
-    pub fn enqueue_specific(&mut self, id: usize, event: Event) {
+    pub fn enqueue_specific(&mut self, id: Handle, event: Event) {
         self.queue.borrow_mut().push((id, Rc::new(event)));
     }
 
-    pub fn enqueue_specific_rc(&mut self, id: usize, event: Rc<Event>) {
+    pub fn enqueue_specific_rc(&mut self, id: Handle, event: Rc<Event>) {
         self.queue.borrow_mut().push((id, event));
     }
 
@@ -200,7 +337,7 @@ impl<E: Entity> Handler<E> {
         self.sysqueue.push(event);
     }
 
-    pub fn flush(&mut self) -> Vec<(usize, Rc<Event>)> {
+    pub fn flush(&mut self) -> Vec<(Handle, Rc<Event>)> {
         mem::replace(&mut self.queue.borrow_mut(), Default::default())
     }
 
@@ -209,9 +346,57 @@ impl<E: Entity> Handler<E> {
     }
 }
 
+/// Entity which ignores every event, for tests which only need the type
+#[cfg(test)]
+pub struct Dummy;
+
+#[cfg(test)]
+impl Entity for Dummy {
+    fn handle_event(&mut self, _e: Rc<Event>) { }
+    fn id(&self) -> Handle { Default::default() }
+}
+
 #[test]
 fn test_eventmap() {
     let mut evs = util::hashmap();
     evs.insert(Event::Update(0.0), 0);
     assert_eq!(evs.get(&Event::Update(1.0)).unwrap(), &0);
 }
+
+#[test]
+fn test_custom_channels() {
+    struct Ping(u8);
+    struct Pong;
+
+    let mut handler: Handler<Dummy> = Handler::new();
+    let id = Handle { index: 1, generation: 0 };
+    handler.subscribe(id, Event::Custom(CustomEvent::channel::<Ping>()));
+    handler.enqueue_all(Event::Custom(CustomEvent::new(Pong)));
+    handler.enqueue_all(Event::Custom(CustomEvent::new(Ping(3))));
+    let queued = handler.flush();
+    assert_eq!(queued.len(), 1);
+    match *queued[0].1 {
+        Event::Custom(ref c) => {
+            assert!(c.get::<Pong>().is_none());
+            assert_eq!(c.get::<Ping>().unwrap().0, 3);
+        }
+        _ => panic!("Expected a custom event"),
+    }
+}
+
+#[test]
+fn test_dispatch_order() {
+    let handle = |index| Handle { index: index, generation: 0 };
+    let mut handler: Handler<Dummy> = Handler::new();
+    // Ids are recycled, so spawn order shouldn't follow them
+    handler.register(handle(7));
+    handler.register(handle(3));
+    handler.register(handle(5));
+    for id in &[3, 5, 7] {
+        handler.subscribe(handle(*id), Event::Render);
+    }
+    handler.set_priority(handle(5), -1);
+    handler.enqueue_all(Event::Render);
+    let order: Vec<_> = handler.flush().into_iter().map(|(id, _)| id.index).collect();
+    assert_eq!(order, vec![5, 7, 3]);
+}
@@ -0,0 +1,256 @@
+use std::mem;
+use std::fs::File;
+use glium::program::Program;
+use glium::VertexBuffer;
+use glium::index::IndexBuffer;
+use glium::draw_parameters::DrawParameters;
+use glium::uniforms::Uniforms;
+use glium::{DisplayBuild, Surface};
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::texture::compressed_srgb_texture2d::CompressedSrgbTexture2d;
+use glium::glutin;
+use glium::{self, Frame};
+use glium_text::{self, FontTexture, TextSystem, TextDisplay};
+use imgui::{ImGui, Ui};
+use imgui::glium_renderer::Renderer;
+
+use util::{self, HashMap};
+use graphics::{GraphicsBackend, SpriteAttrs, SpriteVertex};
+
+/// Windowed backend rendering through glium
+pub struct GlGraphics {
+    sprites: HashMap<usize, SpriteBuffers>,
+    custom_sprites: HashMap<usize, CustomSpriteBuffers>,
+    display: GlutinFacade,
+    fonts: HashMap<usize, FontTexture>,
+    tex_sys: TextSystem,
+    current_frame: Option<Frame>,
+}
+
+struct SpriteBuffers {
+    program: Program,
+    vbo: VertexBuffer<SpriteVertex>,
+    vertex_attrs: VertexBuffer<SpriteAttrs>,
+    indices: IndexBuffer<u16>,
+    texture: Option<CompressedSrgbTexture2d>,
+}
+
+struct CustomSpriteBuffers {
+    program: Program,
+    vbo: VertexBuffer<SpriteVertex>,
+    indices: IndexBuffer<u16>,
+}
+
+impl GlGraphics {
+    pub fn new((x_res, y_res): (u32, u32)) -> GlGraphics {
+        let display = glium::glutin::WindowBuilder::new()
+                          .with_dimensions(x_res, y_res)
+                          .with_title(String::from("Nishikaku"))
+                          .with_max_dimensions(x_res, y_res)
+                          .with_min_dimensions(x_res, y_res)
+                          .with_vsync()
+                          .build_glium()
+                          .unwrap();
+        let tex_sys = TextSystem::new(&display);
+        GlGraphics {
+            sprites: util::hashmap(),
+            custom_sprites: util::hashmap(),
+            display: display,
+            fonts: util::hashmap(),
+            current_frame: None,
+            tex_sys: tex_sys,
+        }
+    }
+
+    fn load_texture(&self, path: &str) -> CompressedSrgbTexture2d {
+        use image;
+
+        let f = File::open(path).unwrap();
+        let image = image::load(f, image::PNG)
+                        .unwrap()
+                        .to_rgba();
+        let image_dimensions = image.dimensions();
+        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(),
+                                                                       image_dimensions);
+        glium::texture::CompressedSrgbTexture2d::new(&self.display, image).unwrap()
+    }
+
+    fn make_indices(&self) -> IndexBuffer<u16> {
+        IndexBuffer::new(&self.display,
+                         glium::index::PrimitiveType::TriangleStrip,
+                         &[1 as u16, 2, 0, 3])
+            .unwrap()
+    }
+
+    pub fn render_custom<U: Uniforms>(&mut self,
+                                      sprite: &usize,
+                                      uniforms: &U,
+                                      params: &DrawParameters) {
+        match (&mut self.current_frame, self.custom_sprites.get(sprite)) {
+            (&mut Some(ref mut target), Some(sprite_data)) => {
+                target.draw(&sprite_data.vbo,
+                            &sprite_data.indices,
+                            &sprite_data.program,
+                            uniforms,
+                            &params)
+                      .unwrap();
+            }
+            (&mut None, _) => {
+                println!("Cannot render custom sprite without initialized frame!");
+            }
+            (_, None) => {
+                println!("Invalid custom sprite identifier passed!");
+            }
+        }
+    }
+}
+
+impl GraphicsBackend for GlGraphics {
+    fn new_sprite(&mut self,
+                  id: usize,
+                  vertex_shader: &str,
+                  fragment_shader: &str,
+                  vertices: &[SpriteVertex],
+                  texture: Option<&str>,
+                  max_amount: usize) {
+        let prog = Program::from_source(&self.display, vertex_shader, fragment_shader, None)
+                       .unwrap();
+        let vbo = VertexBuffer::new(&self.display, vertices).unwrap();
+        let vertex_attrs = VertexBuffer::empty_dynamic(&self.display, max_amount).unwrap();
+        let texture = texture.map(|path| self.load_texture(path));
+        let data = SpriteBuffers {
+            program: prog,
+            vbo: vbo,
+            indices: self.make_indices(),
+            vertex_attrs: vertex_attrs,
+            texture: texture,
+        };
+        self.sprites.insert(id, data);
+    }
+
+    fn new_custom_sprite(&mut self,
+                         id: usize,
+                         vertex_shader: &str,
+                         fragment_shader: &str,
+                         vertices: &[SpriteVertex]) {
+        let prog = Program::from_source(&self.display, vertex_shader, fragment_shader, None)
+                       .unwrap();
+        let vbo = VertexBuffer::new(&self.display, vertices).unwrap();
+        let data = CustomSpriteBuffers {
+            program: prog,
+            vbo: vbo,
+            indices: self.make_indices(),
+        };
+        self.custom_sprites.insert(id, data);
+    }
+
+    fn load_font(&mut self, id: usize, path: &str) {
+        let f = File::open(path).unwrap();
+        let font = FontTexture::new(&self.display, f, 36).unwrap();
+        self.fonts.insert(id, font);
+    }
+
+    fn start_frame(&mut self) {
+        let mut target = self.display.draw();
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+        self.current_frame = Some(target);
+    }
+
+    fn draw_sprites(&mut self, sprite: &usize, attrs: &[SpriteAttrs]) {
+        use glium::Blend;
+
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+        match (&mut self.current_frame, self.sprites.get_mut(sprite)) {
+            (&mut Some(ref mut target), Some(sprite_data)) => {
+                sprite_data.vertex_attrs.write(attrs);
+                if let Some(ref tex) = sprite_data.texture {
+                    let uniforms = uniform! {
+                        tex: tex,
+                    };
+                    target.draw((&sprite_data.vbo,
+                                 sprite_data.vertex_attrs.per_instance().unwrap()),
+                                &sprite_data.indices,
+                                &sprite_data.program,
+                                &uniforms,
+                                &params)
+                          .unwrap();
+                } else {
+                    let uniforms = uniform![];
+                    target.draw((&sprite_data.vbo,
+                                 sprite_data.vertex_attrs.per_instance().unwrap()),
+                                &sprite_data.indices,
+                                &sprite_data.program,
+                                &uniforms,
+                                &params)
+                          .unwrap();
+                }
+            }
+            (&mut None, _) => {
+                println!("Frame must be started before rendering can occur!");
+            }
+            (_, None) => {
+                println!("Invalid sprite identifier passed!");
+            }
+        }
+    }
+
+    fn render_text(&mut self,
+                   id: &usize,
+                   msg: &str,
+                   transform: &[[f32; 4]; 4],
+                   color: &(f32, f32, f32, f32)) {
+        match self.current_frame {
+            Some(ref mut target) => {
+                if let Some(font) = self.fonts.get(id) {
+                    let text = TextDisplay::new(&self.tex_sys, font, msg);
+                    glium_text::draw(&text, &self.tex_sys, target, *transform, *color);
+                }
+            }
+            None => {
+                println!("Cannot render text without initialized frame!");
+            }
+        }
+    }
+
+    fn finish_frame(&mut self) {
+        if self.current_frame.is_none() {
+            println!("A frame must be started before being finished!");
+        } else {
+            let target = mem::replace(&mut self.current_frame, None);
+            target.unwrap().finish().unwrap();
+        }
+    }
+
+    fn poll_events(&mut self) -> Vec<glutin::Event> {
+        self.display.poll_events().collect()
+    }
+
+    fn make_menu_renderer(&self, imgui: &mut ImGui) -> Option<Renderer> {
+        Some(Renderer::init(imgui, &self.display).unwrap())
+    }
+
+    fn get_menu_frame<'imgui>(&self, imgui: &'imgui mut ImGui, dt: f32) -> Ui<'imgui> {
+        let window = self.display.get_window().unwrap();
+        let size_points = window.get_inner_size_points().unwrap();
+        let size_pixels = window.get_inner_size_pixels().unwrap();
+        imgui.frame(size_points, size_pixels, dt)
+    }
+
+    fn render_menu(&mut self, renderer: &mut Renderer, ui_frame: Ui) {
+        match self.current_frame {
+            Some(ref mut target) => {
+                renderer.render(target, ui_frame).unwrap();
+            }
+            None => {
+                println!("Cannot render menu without initialized frame!");
+            }
+        }
+    }
+
+    fn as_gl(&mut self) -> Option<&mut GlGraphics> {
+        Some(self)
+    }
+}
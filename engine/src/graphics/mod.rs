@@ -1,53 +1,121 @@
-use std::mem;
-use std::fs::File;
 use ncollide_geometry::shape::ShapeHandle2;
-use glium::program::Program;
-use glium::VertexBuffer;
-use glium::index::IndexBuffer;
 use glium::draw_parameters::DrawParameters;
 use glium::uniforms::Uniforms;
-use glium::{DisplayBuild, Surface};
-use glium::backend::glutin_backend::{GlutinFacade, PollEventsIter};
-use glium::texture::compressed_srgb_texture2d::CompressedSrgbTexture2d;
-use glium::{self, Frame};
-use glium_text::{self, FontTexture, TextSystem, TextDisplay};
+use glium::glutin;
 use imgui::{ImGui, Ui};
 use imgui::glium_renderer::Renderer;
 
-use util::{self, HashMap};
+use util::{self, HashMap, HashSet};
 use scene::Registry;
 
+mod gl;
+pub use self::gl::GlGraphics;
+
 // TODO: Using reversed matrices is probably a bad practice, invert everything
 
+/// Everything that needs a window or GL context goes through a backend,
+/// sprite bookkeeping stays in `Graphics` so components work the same on any backend
+pub trait GraphicsBackend {
+    fn new_sprite(&mut self,
+                  id: usize,
+                  vertex_shader: &str,
+                  fragment_shader: &str,
+                  vertices: &[SpriteVertex],
+                  texture: Option<&str>,
+                  max_amount: usize);
+    fn new_custom_sprite(&mut self,
+                         id: usize,
+                         vertex_shader: &str,
+                         fragment_shader: &str,
+                         vertices: &[SpriteVertex]);
+    fn load_font(&mut self, id: usize, path: &str);
+    fn start_frame(&mut self);
+    fn draw_sprites(&mut self, sprite: &usize, attrs: &[SpriteAttrs]);
+    fn render_text(&mut self,
+                   id: &usize,
+                   msg: &str,
+                   transform: &[[f32; 4]; 4],
+                   color: &(f32, f32, f32, f32));
+    fn finish_frame(&mut self);
+    fn poll_events(&mut self) -> Vec<glutin::Event>;
+    /// Returns None if the backend can't draw menus, frames are still built but discarded
+    fn make_menu_renderer(&self, imgui: &mut ImGui) -> Option<Renderer>;
+    fn get_menu_frame<'imgui>(&self, imgui: &'imgui mut ImGui, dt: f32) -> Ui<'imgui>;
+    fn render_menu(&mut self, renderer: &mut Renderer, ui_frame: Ui);
+
+    fn as_gl(&mut self) -> Option<&mut GlGraphics> {
+        None
+    }
+}
+
+/// Backend which draws nothing, used when running without a window
+pub struct NullGraphics {
+    dimensions: (u32, u32),
+    fonts: HashSet<usize>,
+}
+
+impl NullGraphics {
+    pub fn new(dimensions: (u32, u32)) -> NullGraphics {
+        NullGraphics {
+            dimensions: dimensions,
+            fonts: util::hashset(),
+        }
+    }
+}
+
+impl GraphicsBackend for NullGraphics {
+    fn new_sprite(&mut self, _id: usize, _vs: &str, _fs: &str, _vertices: &[SpriteVertex], _texture: Option<&str>, _max_amount: usize) { }
+
+    fn new_custom_sprite(&mut self, _id: usize, _vs: &str, _fs: &str, _vertices: &[SpriteVertex]) { }
+
+    fn load_font(&mut self, id: usize, _path: &str) {
+        self.fonts.insert(id);
+    }
+
+    fn start_frame(&mut self) { }
+
+    fn draw_sprites(&mut self, _sprite: &usize, _attrs: &[SpriteAttrs]) { }
+
+    fn render_text(&mut self, id: &usize, _msg: &str, _transform: &[[f32; 4]; 4], _color: &(f32, f32, f32, f32)) {
+        if !self.fonts.contains(id) {
+            println!("Tried to render text with invalid font: {}", id);
+        }
+    }
+
+    fn finish_frame(&mut self) { }
+
+    fn poll_events(&mut self) -> Vec<glutin::Event> {
+        vec![]
+    }
+
+    fn make_menu_renderer(&self, imgui: &mut ImGui) -> Option<Renderer> {
+        // ImGui won't build frames until its font atlas exists
+        imgui.prepare_texture(|_| ());
+        None
+    }
+
+    fn get_menu_frame<'imgui>(&self, imgui: &'imgui mut ImGui, dt: f32) -> Ui<'imgui> {
+        imgui.frame(self.dimensions, self.dimensions, dt)
+    }
+
+    fn render_menu(&mut self, _renderer: &mut Renderer, _ui_frame: Ui) { }
+}
+
 pub struct Graphics {
-    custom_sprites: HashMap<usize, CustomSpriteData>,
+    custom_sprites: HashMap<usize, Option<ShapeHandle2<f32>>>,
     sprites: HashMap<usize, SpriteData>,
-    display: GlutinFacade,
-    fonts: HashMap<usize, FontTexture>,
-    tex_sys: TextSystem,
-    current_frame: Option<Frame>,
+    backend: Box<GraphicsBackend>,
     pub dimensions: (u32, u32),
     pub scaler: f32,
 }
 
 struct SpriteData {
-    program: Program,
-    vbo: VertexBuffer<SpriteVertex>,
     pre_render: Vec<SpriteAttrs>,
-    vertex_attrs: VertexBuffer<SpriteAttrs>,
-    indices: IndexBuffer<u16>,
-    texture: Option<CompressedSrgbTexture2d>,
     shape: Option<ShapeHandle2<f32>>,
+    size: (f32, f32),
     pub registry: Registry,
 }
 
-struct CustomSpriteData {
-    program: Program,
-    vbo: VertexBuffer<SpriteVertex>,
-    indices: IndexBuffer<u16>,
-    shape: Option<ShapeHandle2<f32>>,
-}
-
 use std::fmt;
 impl fmt::Debug for SpriteData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -56,53 +124,42 @@ impl fmt::Debug for SpriteData {
 }
 
 impl Graphics {
-    pub fn new((x_res, y_res): (u32, u32), scaler: f32) -> Graphics {
-        let display = glium::glutin::WindowBuilder::new()
-                          .with_dimensions(x_res, y_res)
-                          .with_title(String::from("Nishikaku"))
-                          .with_max_dimensions(x_res, y_res)
-                          .with_min_dimensions(x_res, y_res)
-                          .with_vsync()
-                          .build_glium()
-                          .unwrap();
-        let tex_sys = TextSystem::new(&display);
+    pub fn new(backend: Box<GraphicsBackend>, dimensions: (u32, u32), scaler: f32) -> Graphics {
         Graphics {
             scaler: scaler,
             sprites: util::hashmap(),
             custom_sprites: util::hashmap(),
-            display: display,
-            fonts: util::hashmap(),
-            current_frame: None,
-            tex_sys: tex_sys,
-            dimensions: (x_res, y_res),
+            backend: backend,
+            dimensions: dimensions,
         }
     }
 
+    pub fn windowed(dimensions: (u32, u32), scaler: f32) -> Graphics {
+        Graphics::new(Box::new(GlGraphics::new(dimensions)), dimensions, scaler)
+    }
+
+    pub fn headless(dimensions: (u32, u32), scaler: f32) -> Graphics {
+        Graphics::new(Box::new(NullGraphics::new(dimensions)), dimensions, scaler)
+    }
+
     pub fn new_sprite(&mut self,
                       id: usize,
                       vertex_shader: &str,
                       fragment_shader: &str,
-                      vbo: VertexBuffer<SpriteVertex>,
-                      texture: Option<CompressedSrgbTexture2d>,
+                      vertices: &[SpriteVertex],
+                      texture: Option<&str>,
                       max_amount: usize,
                       shape: Option<ShapeHandle2<f32>>) {
-        let prog = Program::from_source(&self.display, vertex_shader, fragment_shader, None)
-                       .unwrap();
-        let vertex_attrs = VertexBuffer::empty_dynamic(&self.display, max_amount).unwrap();
-        let indices = IndexBuffer::new(&self.display,
-                                       glium::index::PrimitiveType::TriangleStrip,
-                                       &[1 as u16, 2, 0, 3])
-                          .unwrap();
+        self.backend.new_sprite(id, vertex_shader, fragment_shader, vertices, texture, max_amount);
         let mut reg = Registry::new();
         reg.no_reclaim();
+        let size = vertices.iter().fold((0.0f32, 0.0f32), |(w, h), v| {
+            (w.max(2.0 * v.position[0].abs() * self.scaler), h.max(2.0 * v.position[1].abs() * self.scaler))
+        });
         let data = SpriteData {
-            program: prog,
-            vbo: vbo,
-            indices: indices,
-            vertex_attrs: vertex_attrs,
-            texture: texture,
             registry: reg,
             shape: shape,
+            size: size,
             pre_render: vec![SpriteAttrs::hidden(); max_amount],
         };
         self.sprites.insert(id, data);
@@ -116,26 +173,23 @@ impl Graphics {
                              id: usize,
                              vertex_shader: &str,
                              fragment_shader: &str,
-                             vbo: VertexBuffer<SpriteVertex>,
+                             vertices: &[SpriteVertex],
                              shape: Option<ShapeHandle2<f32>>) {
-        let prog = Program::from_source(&self.display, vertex_shader, fragment_shader, None)
-                       .unwrap();
-        let indices = IndexBuffer::new(&self.display,
-                                       glium::index::PrimitiveType::TriangleStrip,
-                                       &[1 as u16, 2, 0, 3])
-                          .unwrap();
-        let data = CustomSpriteData {
-            program: prog,
-            vbo: vbo,
-            indices: indices,
-            shape: shape,
-        };
-        self.custom_sprites.insert(id, data);
+        self.backend.new_custom_sprite(id, vertex_shader, fragment_shader, vertices);
+        self.custom_sprites.insert(id, shape);
     }
 
+    /// Reserves an instance of the sprite, none once all of its `max_amount` instances are taken
     pub fn get_id(&mut self, sprite: &usize) -> Option<usize> {
         match self.sprites.get_mut(sprite) {
-            Some(s) => Some(s.registry.get_id()),
+            Some(s) => {
+                let id = s.registry.get_id();
+                if id > s.pre_render.len() {
+                    s.registry.return_id(id);
+                    return None;
+                }
+                Some(id)
+            }
             None => None,
         }
     }
@@ -154,69 +208,51 @@ impl Graphics {
         }
     }
 
+    /// Width and height of the sprite in world units
+    pub fn get_sprite_size(&self, sprite: &usize) -> Option<(f32, f32)> {
+        self.sprites.get(sprite).map(|s| s.size)
+    }
+
     pub fn set_sprite_attr(&mut self, sprite: &usize, pos: usize, attrs: &SpriteAttrs) {
         match self.sprites.get_mut(sprite) {
             Some(s) => {
                 // Starts at 1 in registry
                 s.pre_render[pos-1] = *attrs;
-                // match s.vertex_attrs.slice_mut((pos - 1)..(pos)) {
-                //     Some(slice) => slice.write(&[*attrs]),
-                //     None => panic!(format!("Failed to write to sprite {:?} at pos {:?}", sprite, pos))
-                // }
             }
             None => {}
         }
     }
 
-    pub fn make_sprite_vbo(&self, vertices: &[SpriteVertex]) -> VertexBuffer<SpriteVertex> {
-        VertexBuffer::new(&self.display, vertices).unwrap()
-    }
-
-    pub fn load_texture(&self, path: &str) -> CompressedSrgbTexture2d {
-        use std::fs::File;
-        use image;
-
-        let f = File::open(path).unwrap();
-        let image = image::load(f, image::PNG)
-                        .unwrap()
-                        .to_rgba();
-        let image_dimensions = image.dimensions();
-        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(),
-                                                                       image_dimensions);
-        glium::texture::CompressedSrgbTexture2d::new(&self.display, image).unwrap()
+    /// Writes the attributes of many instances of a sprite at once, `ids` and `attrs` are paired up
+    pub fn set_sprite_attrs(&mut self, sprite: &usize, ids: &[usize], attrs: &[SpriteAttrs]) {
+        if let Some(s) = self.sprites.get_mut(sprite) {
+            for (id, attr) in ids.iter().zip(attrs.iter()) {
+                if let Some(slot) = s.pre_render.get_mut(id - 1) {
+                    *slot = *attr;
+                }
+            }
+        }
     }
 
     pub fn load_font(&mut self, id: usize, path: &str) {
-        let f = File::open(path).unwrap();
-        let font = FontTexture::new(&self.display, f, 36).unwrap();
-        self.fonts.insert(id, font);
+        self.backend.load_font(id, path);
     }
 
-    pub fn make_menu_renderer(&self, imgui: &mut ImGui) -> Renderer {
-        Renderer::init(imgui, &self.display).unwrap()
+    pub fn make_menu_renderer(&self, imgui: &mut ImGui) -> Option<Renderer> {
+        self.backend.make_menu_renderer(imgui)
     }
 
     pub fn start_frame(&mut self) {
-        let mut target = self.display.draw();
-        target.clear_color(0.0, 0.0, 0.0, 1.0);
-        self.current_frame = Some(target);
+        self.backend.start_frame();
     }
 
     pub fn get_menu_frame<'imgui>(&self, imgui: &'imgui mut ImGui, dt: f32) -> Ui<'imgui> {
-        let window = self.display.get_window().unwrap();
-        let size_points = window.get_inner_size_points().unwrap();
-        let size_pixels = window.get_inner_size_pixels().unwrap();
-        imgui.frame(size_points, size_pixels, dt)
+        self.backend.get_menu_frame(imgui, dt)
     }
 
-    pub fn render_menu(&mut self, renderer: &mut Renderer, ui_frame: Ui) {
-        match self.current_frame {
-            Some(ref mut target) => {
-                renderer.render(target, ui_frame).unwrap();
-            }
-            None => {
-                println!("Cannot render menu without initialized frame!");
-            }
+    pub fn render_menu(&mut self, renderer: Option<&mut Renderer>, ui_frame: Ui) {
+        if let Some(renderer) = renderer {
+            self.backend.render_menu(renderer, ui_frame);
         }
     }
 
@@ -224,21 +260,8 @@ impl Graphics {
                                       sprite: &usize,
                                       uniforms: &U,
                                       params: &DrawParameters) {
-        match (&mut self.current_frame, self.custom_sprites.get(sprite)) {
-            (&mut Some(ref mut target), Some(sprite_data)) => {
-                target.draw(&sprite_data.vbo,
-                            &sprite_data.indices,
-                            &sprite_data.program,
-                            uniforms,
-                            &params)
-                      .unwrap();
-            }
-            (&mut None, _) => {
-                println!("Cannot render custom sprite without initialized frame!");
-            }
-            (_, None) => {
-                println!("Invalid custom sprite identifier passed!");
-            }
+        if let Some(gl) = self.backend.as_gl() {
+            gl.render_custom(sprite, uniforms, params);
         }
     }
 
@@ -247,70 +270,21 @@ impl Graphics {
                        msg: &str,
                        transform: &[[f32; 4]; 4],
                        color: &(f32, f32, f32, f32)) {
-        match self.current_frame {
-            Some(ref mut target) => {
-                if let Some(font) = self.fonts.get(id) {
-                    let text = TextDisplay::new(&self.tex_sys, font, msg);
-                    glium_text::draw(&text, &self.tex_sys, target, *transform, *color);
-                }
-            }
-            None => {
-                println!("Cannot render text without initialized frame!");
-            }
-        }
+        self.backend.render_text(id, msg, transform, color);
     }
 
     pub fn render(&mut self) {
-        use glium::Blend;
-
-        let params = DrawParameters {
-            blend: Blend::alpha_blending(),
-            ..Default::default()
-        };
-        match self.current_frame {
-            Some(ref mut target) => {
-                for (&_, ref mut sprite_data) in self.sprites.iter_mut() {
-                    sprite_data.vertex_attrs.write(&sprite_data.pre_render[..]);
-                    if let Some(ref tex) = sprite_data.texture {
-                        let uniforms = uniform! {
-                            tex: tex,
-                        };
-                        target.draw((&sprite_data.vbo,
-                                     sprite_data.vertex_attrs.per_instance().unwrap()),
-                                    &sprite_data.indices,
-                                    &sprite_data.program,
-                                    &uniforms,
-                                    &params)
-                              .unwrap();
-                    } else {
-                        let uniforms = uniform![];
-                        target.draw((&sprite_data.vbo,
-                                     sprite_data.vertex_attrs.per_instance().unwrap()),
-                                    &sprite_data.indices,
-                                    &sprite_data.program,
-                                    &uniforms,
-                                    &params)
-                              .unwrap();
-                    }
-                }
-            }
-            None => {
-                println!("Frame must be started before rendering can occur!");
-            }
+        for (id, sprite_data) in self.sprites.iter() {
+            self.backend.draw_sprites(id, &sprite_data.pre_render[..]);
         }
     }
 
     pub fn finish_frame(&mut self) {
-        if self.current_frame.is_none() {
-            println!("A frame must be started before being finished!");
-        } else {
-            let target = mem::replace(&mut self.current_frame, None);
-            target.unwrap().finish().unwrap();
-        }
+        self.backend.finish_frame();
     }
 
-    pub fn get_window_events(&self) -> PollEventsIter {
-        self.display.poll_events()
+    pub fn get_window_events(&mut self) -> Vec<glutin::Event> {
+        self.backend.poll_events()
     }
 }
 
@@ -339,10 +313,15 @@ impl SpriteAttrs {
     }
 
     pub fn set_rot(&mut self, t: f32) {
-        self.transform[0][0] = t.cos();
-        self.transform[0][1] = -1.0 * t.sin();
-        self.transform[1][0] = t.sin();
-        self.transform[1][1] = t.cos();
+        self.set_rot_scale(t, 1.0, 1.0);
+    }
+
+    /// Rotates the sprite after scaling it along its own x and y axes
+    pub fn set_rot_scale(&mut self, t: f32, sx: f32, sy: f32) {
+        self.transform[0][0] = t.cos() * sx;
+        self.transform[0][1] = -1.0 * t.sin() * sy;
+        self.transform[1][0] = t.sin() * sx;
+        self.transform[1][1] = t.cos() * sy;
     }
 
     pub fn set_pos(&mut self, x: f32, y: f32) {
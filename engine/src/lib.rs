@@ -17,23 +17,62 @@ pub mod graphics;
 pub mod physics;
 pub mod audio;
 pub mod util;
+pub mod replay;
+pub mod snapshot;
+pub mod time;
+pub mod resource;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::thread;
 use std::time::Duration;
 use std::ops::Deref;
+use std::mem;
 use glium::glutin;
 
+/// Length of a single simulation step in seconds
+pub const TIMESTEP: f32 = 0.016666667;
+const FRAME_DELAY_NANOSECS: u64 = 16666667;
+
 pub struct Engine<E: entity::Entity> {
     pub events: Rc<RefCell<event::Handler<E>>>,
     pub scene: scene::Scene<E>,
     pub graphics: Rc<RefCell<graphics::Graphics>>,
     pub audio: Rc<RefCell<audio::Audio>>,
+    pub resources: Rc<RefCell<resource::Resources>>,
+    input: Vec<event::Event>,
+    key_counter: [u8; 255],
+    ticks: u64,
+    session: Session,
+    checkpoints: snapshot::Checkpoints<E>,
+    seeking: bool,
+}
+
+enum Session {
+    Idle,
+    /// Waiting for the game to start a session before playing back
+    Armed(replay::Replay),
+    Recording(replay::Replay, u64),
+    Playing(replay::Replay, u64, usize),
 }
 
 impl<E: entity::Entity> Engine<E> {
     pub fn new(size: f32, res: u32, p: scene::PhysicsHandler) -> Engine<E> {
+        let graphics = graphics::Graphics::windowed((res, res), size);
+        Engine::with_backends(size, p, graphics, audio::Audio::sdl())
+    }
+
+    /// Creates an engine which never opens a window, GL context or sound device
+    pub fn headless(size: f32, res: u32, p: scene::PhysicsHandler) -> Engine<E> {
+        let graphics = graphics::Graphics::headless((res, res), size);
+        Engine::with_backends(size, p, graphics, audio::Audio::headless())
+    }
+
+    pub fn with_backends(size: f32,
+                         p: scene::PhysicsHandler,
+                         graphics: graphics::Graphics,
+                         audio: audio::Audio)
+                         -> Engine<E> {
         let scene = scene::Scene::new(size, p);
         let eh = event::Handler::new();
         let events = Rc::new(RefCell::new(eh));
@@ -41,149 +80,665 @@ impl<E: entity::Entity> Engine<E> {
         Engine {
             events: events,
             scene: scene,
-            graphics: Rc::new(RefCell::new(graphics::Graphics::new((res, res), size))),
-            audio: Rc::new(RefCell::new(audio::Audio::new())),
+            graphics: Rc::new(RefCell::new(graphics)),
+            audio: Rc::new(RefCell::new(audio)),
+            resources: Rc::new(RefCell::new(resource::Resources::new())),
+            input: vec![],
+            key_counter: [0; 255],
+            ticks: 0,
+            session: Session::Idle,
+            checkpoints: snapshot::Checkpoints::new(),
+            seeking: false,
         }
     }
 
-    pub fn spawn_entity(&self, mut e: E) {
+    pub fn spawn_entity(&self, e: E) {
+        self.insert_entity(e, event::Event::Spawn);
+    }
+
+    fn insert_entity(&self, mut e: E, event: event::Event) {
         let id = e.id();
+        self.events.deref().borrow_mut().register(id);
         self.events.deref().borrow_mut().subscribe(id.clone(), event::Event::Update(0.0));
         self.events.deref().borrow_mut().subscribe(id.clone(), event::Event::Render);
-        e.handle_event(Rc::new(event::Event::Spawn));
+        e.handle_event(Rc::new(event));
         self.scene.world.deref().insert(id, e);
     }
 
-    pub fn spawn(&self, spawner: Box<Fn(&Engine<E>) -> E>) -> usize {
-        let mut e = spawner(&self);
+    pub fn spawn(&self, spawner: Box<Fn(&Engine<E>) -> E>) -> scene::Handle {
+        let e = spawner(&self);
         let id = e.id();
-        self.events.deref().borrow_mut().subscribe(id.clone(), event::Event::Update(0.0));
-        self.events.deref().borrow_mut().subscribe(id.clone(), event::Event::Render);
-        e.handle_event(Rc::new(event::Event::Spawn));
-        self.scene.world.deref().insert(id, e);
+        self.spawn_entity(e);
         id
     }
 
-    pub fn destroy(&self, id: usize) {
+    /// Sends `Event::Despawn` to the entity, removes it and then notifies subscribers of
+    /// `Event::Destroyed`. Destroying an entity which is already gone does nothing
+    pub fn destroy(&self, id: scene::Handle) {
+        if !self.scene.world.contains(&id) {
+            return;
+        }
+        self.scene.dispatch(id, Rc::new(event::Event::Despawn));
         self.scene.world.deref().remove(&id);
+        let mut events = self.events.deref().borrow_mut();
+        events.unsubscribe_all(id);
+        events.enqueue_all(event::Event::Destroyed(id));
     }
 
     pub fn handle_events(&mut self) {
         let ev_queue = {
             self.events.deref().borrow_mut().flush()
         };
+        // Events still queued for destroyed entities are dropped
         for (id, event) in ev_queue {
-            if id != 0 {
-                self.scene.dispatch(id, event);
-            } else {
+            if id.index == 0 {
                 self.handle_internal_event(event);
+            } else if self.scene.world.contains(&id) {
+                self.scene.dispatch(id, event);
             }
         }
     }
 
-    pub fn run(&mut self) {
-        let mut previous_clock = clock_ticks::precise_time_ns();
-        let mut accumulator = 0;
+    /// Captures every entity which supports snapshots, in dispatch order, and the resources kept in snapshots
+    pub fn snapshot(&self) -> snapshot::Snapshot<E> {
+        let mut spawners: Vec<_> = {
+            let events = self.events.deref().borrow();
+            let entities = self.scene.world.entities.borrow();
+            entities.iter()
+                .filter_map(|(id, e)| e.borrow().snapshot().map(|s| (events.dispatch_key(id), s)))
+                .collect()
+        };
+        spawners.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot::Snapshot {
+            spawners: spawners.into_iter().map(|(_, s)| s).collect(),
+            resources: self.resources.borrow().snapshot(),
+        }
+    }
 
-        let mut key_counter = [0 as u8; 255];
-        let res_x = self.graphics.borrow().dimensions.0 as f32;
-        let res_y = self.graphics.borrow().dimensions.1 as f32;
-        let scaler = self.scene.physics.scaler;
-        loop {
-            // TODO: Figure out a cleaner way to get prioritiztion of custom sprites/text over generic sprites
-            // Maybe just don't care and force text to be used with custom sprites?
-            let now = clock_ticks::precise_time_ns();
+    /// Replaces every entity which supports snapshots with the ones stored in `snapshot`.
+    /// Queued events are dropped, and restored entities get `Event::Restore` instead of `Event::Spawn`
+    pub fn restore(&mut self, snapshot: &snapshot::Snapshot<E>) {
+        {
+            let mut events = self.events.deref().borrow_mut();
+            events.flush();
+            events.flush_sys();
+        }
+        let mut stale: Vec<_> = {
+            let entities = self.scene.world.entities.borrow();
+            entities.iter()
+                .filter(|&(_, e)| e.borrow().snapshot().is_some())
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        stale.sort();
+        for id in stale {
+            self.destroy(id);
+        }
+        // After despawning, so the stale entities can't overwrite the restored resources
+        self.resources.borrow_mut().restore(&snapshot.resources);
+        for spawner in snapshot.spawners.iter() {
+            let e = spawner(self);
+            self.insert_entity(e, event::Event::Restore);
+        }
+    }
 
-            accumulator += now - previous_clock;
-            previous_clock = now;
-            const FRAME_DELAY_NANOSECS: u64 = 16666667;
-
-            for event in self.graphics.borrow_mut().get_window_events() {
-                let to_queue = match event {
-                    glutin::Event::Closed => {
-                        use std::fs;
-                        println!("Game shutting down!");
-                        match fs::remove_file("imgui.ini") {
-                            _ => { }
-                        }
-                        return;
-                    },
-                    glutin::Event::KeyboardInput(glutin::ElementState::Pressed, n, c) => {
-                        if key_counter[n as usize] == 0 && !c.is_none() {
-                            key_counter[n as usize] = 1;
-                            Some(event::Event::KeyInput(event::InputState::Pressed, c.unwrap()))
-                        } else {
-                            None
-                        }
+    /// Brings a freshly started `timeline` to `time`. The latest checkpoint before `time` is
+    /// restored and only the remainder is simulated, checkpoints are kept until another timeline is sought
+    pub fn seek(&mut self, timeline: &str, time: f32) {
+        self.checkpoints.select(timeline);
+        let mut base = 0.0;
+        if let Some((t, snapshot)) = self.checkpoints.latest(time) {
+            println!("Restoring checkpoint at {:?}", t);
+            self.restore(&snapshot);
+            base = t;
+        }
+        let steps = ((time - base) / TIMESTEP).round();
+        println!("Seeking {:?} steps to {:?}!", steps, time);
+        self.seeking = true;
+        for _ in 0..(steps.max(0.0) as u64) {
+            self.advance_simulation(TIMESTEP);
+        }
+        self.seeking = false;
+        if time > 0.0 {
+            let snapshot = self.snapshot();
+            self.checkpoints.insert(time, snapshot);
+        }
+    }
+
+    /// Queues an input event, it is dispatched at the start of the next simulation step
+    pub fn inject(&mut self, event: event::Event) {
+        self.input.push(event);
+    }
+
+    /// Number of simulation steps taken so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Plays `replay` back once the game starts its next session,
+    /// key input from the window is ignored until it finishes
+    pub fn play_replay(&mut self, replay: replay::Replay) {
+        self.session = Session::Armed(replay);
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        match self.session {
+            Session::Armed(_) | Session::Playing(_, _, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Stops recording, returning what was recorded of the current session
+    pub fn take_recording(&mut self) -> Option<replay::Replay> {
+        match mem::replace(&mut self.session, Session::Idle) {
+            Session::Recording(replay, _) => Some(replay),
+            session => {
+                self.session = session;
+                None
+            }
+        }
+    }
+
+    fn start_session(&mut self, level: String, start_time: f32) {
+        self.session = match mem::replace(&mut self.session, Session::Idle) {
+            Session::Armed(replay) => {
+                println!("Playing back replay of {:?}", replay.level);
+                Session::Playing(replay, self.ticks, 0)
+            }
+            _ => Session::Recording(replay::Replay::new(level, start_time), self.ticks),
+        };
+    }
+
+    fn dispatch_input(&mut self) {
+        let input = mem::replace(&mut self.input, vec![]);
+        let ticks = self.ticks;
+        let mut events = self.events.deref().borrow_mut();
+        match self.session {
+            Session::Playing(ref replay, start, ref mut next) => {
+                while *next < replay.inputs.len() && replay.inputs[*next].tick <= ticks - start {
+                    let ref i = replay.inputs[*next];
+                    events.enqueue_all(event::Event::KeyInput(i.state.clone(), i.key));
+                    *next += 1;
+                }
+                for ev in input {
+                    match ev {
+                        event::Event::KeyInput(_, _) => { }
+                        ev => events.enqueue_all(ev),
                     }
-                    glutin::Event::KeyboardInput(glutin::ElementState::Released, n, c) => {
-                        match c {
-                            Some(code) => {
-                                key_counter[n as usize] = 0;
-                                Some(event::Event::KeyInput(event::InputState::Released, code))
-                            }
-                            None => None,
+                }
+            }
+            Session::Recording(ref mut recording, start) => {
+                for ev in input {
+                    if let event::Event::KeyInput(ref state, ref key) = ev {
+                        // Keys a replay can't store are withheld, the replay would diverge otherwise
+                        if replay::key_name(key).is_none() {
+                            continue;
                         }
+                        recording.inputs.push(replay::ReplayInput {
+                            tick: ticks - start,
+                            state: state.clone(),
+                            key: *key,
+                        });
                     }
-                    glutin::Event::MouseMoved(x, y) => Some(event::Event::MouseMove((x as f32 * (2.0 * scaler/res_x) - scaler, -1.0 * (y as f32 * (2.0 * scaler/res_y) - scaler)))),
-                    glutin::Event::MouseInput(glutin::ElementState::Pressed, b) => {
-                        Some(event::Event::MouseInput(event::InputState::Pressed, b))
-                    }
-                    glutin::Event::MouseInput(glutin::ElementState::Released, b) => {
-                        Some(event::Event::MouseInput(event::InputState::Released, b))
+                    events.enqueue_all(ev);
+                }
+            }
+            _ => {
+                for ev in input {
+                    events.enqueue_all(ev);
+                }
+            }
+        }
+    }
+
+    /// Advances the simulation by exactly `steps` fixed steps of `TIMESTEP` seconds scaled by the
+    /// time controller, rendering a single frame afterwards if requested. Paused steps only handle input
+    pub fn tick(&mut self, steps: usize, render: bool) {
+        for _ in 0..steps {
+            let step = self.events.deref().borrow_mut().time.next_step(TIMESTEP);
+            match step {
+                Some(dt) => self.advance_simulation(dt),
+                None => self.idle_simulation(),
+            }
+        }
+        if render {
+            self.render();
+        }
+    }
+
+    pub fn render(&mut self) {
+        // TODO: Figure out a cleaner way to get prioritiztion of custom sprites/text over generic sprites
+        // Maybe just don't care and force text to be used with custom sprites?
+        self.graphics.borrow_mut().start_frame();
+        self.graphics.borrow_mut().render();
+        self.events.deref().borrow_mut().enqueue_all(event::Event::Render);
+        self.events.deref().borrow_mut().enqueue_all(event::Event::RenderCustom);
+        self.events.deref().borrow_mut().enqueue_all(event::Event::RenderMenu);
+        self.handle_events();
+        self.graphics.borrow_mut().finish_frame();
+    }
+
+    /// Converts pending window events into injected input.
+    /// Returns false once the window has been closed.
+    pub fn poll_window(&mut self) -> bool {
+        let res_x = self.graphics.borrow().dimensions.0 as f32;
+        let res_y = self.graphics.borrow().dimensions.1 as f32;
+        let scaler = self.scene.physics.scaler;
+        let window_events = self.graphics.borrow_mut().get_window_events();
+        for event in window_events {
+            let to_queue = match event {
+                glutin::Event::Closed => {
+                    return false;
+                },
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, n, c) => {
+                    if self.key_counter[n as usize] == 0 && !c.is_none() {
+                        self.key_counter[n as usize] = 1;
+                        Some(event::Event::KeyInput(event::InputState::Pressed, c.unwrap()))
+                    } else {
+                        None
                     }
-                    glutin::Event::MouseWheel(d, s) => {
-                        Some(event::Event::MouseScroll(d, s))
+                }
+                glutin::Event::KeyboardInput(glutin::ElementState::Released, n, c) => {
+                    match c {
+                        Some(code) => {
+                            self.key_counter[n as usize] = 0;
+                            Some(event::Event::KeyInput(event::InputState::Released, code))
+                        }
+                        None => None,
                     }
-                    _ => None,
-                };
-                if let Some(ev) = to_queue {
-                    self.events.borrow_mut().enqueue_all(ev);
                 }
+                glutin::Event::MouseMoved(x, y) => Some(event::Event::MouseMove((x as f32 * (2.0 * scaler/res_x) - scaler, -1.0 * (y as f32 * (2.0 * scaler/res_y) - scaler)))),
+                glutin::Event::MouseInput(glutin::ElementState::Pressed, b) => {
+                    Some(event::Event::MouseInput(event::InputState::Pressed, b))
+                }
+                glutin::Event::MouseInput(glutin::ElementState::Released, b) => {
+                    Some(event::Event::MouseInput(event::InputState::Released, b))
+                }
+                glutin::Event::MouseWheel(d, s) => {
+                    Some(event::Event::MouseScroll(d, s))
+                }
+                _ => None,
+            };
+            if let Some(ev) = to_queue {
+                self.inject(ev);
             }
+        }
+        true
+    }
+
+    /// Runs the engine in real time until the window is closed
+    pub fn run(&mut self) {
+        let mut previous_clock = clock_ticks::precise_time_ns();
+        let mut accumulator = 0;
 
-            while accumulator >= FRAME_DELAY_NANOSECS {
-                self.advance_simulation(0.016666667f32);
-                accumulator -= FRAME_DELAY_NANOSECS;
+        loop {
+            let now = clock_ticks::precise_time_ns();
+
+            accumulator += now - previous_clock;
+            previous_clock = now;
+
+            if !self.poll_window() {
+                use std::fs;
+                println!("Game shutting down!");
+                match fs::remove_file("imgui.ini") {
+                    _ => { }
+                }
+                return;
             }
 
-            self.graphics.borrow_mut().start_frame();
-            self.graphics.borrow_mut().render();
-            self.events.deref().borrow_mut().enqueue_all(event::Event::Render);
-            self.events.deref().borrow_mut().enqueue_all(event::Event::RenderCustom);
-            self.events.deref().borrow_mut().enqueue_all(event::Event::RenderMenu);
-            self.handle_events();
-            self.graphics.borrow_mut().finish_frame();
+            let steps = accumulator / FRAME_DELAY_NANOSECS;
+            accumulator -= steps * FRAME_DELAY_NANOSECS;
+            self.tick(steps as usize, true);
 
             thread::sleep(Duration::from_millis(((FRAME_DELAY_NANOSECS - accumulator) / 1000000) as u64));
         }
     }
 
     fn advance_simulation(&mut self, step: f32) {
+        self.dispatch_input();
         self.events.deref().borrow_mut().enqueue_all(event::Event::Update(step));
         self.handle_events();
-        self.scene.update(step);
+        let mut collisions = self.scene.update(step);
+        {
+            let events = self.events.deref().borrow();
+            collisions.sort_by_key(|&(phase, ref c)| {
+                (events.dispatch_key(&c.id1), c.data1.tag, events.dispatch_key(&c.id2), c.data2.tag, c.id1, c.id2, phase)
+            });
+        }
+        self.scene.dispatch_collisions(collisions);
+        self.cull();
+        self.ticks += 1;
+        self.handle_sys_events();
+    }
+
+    /// Notifies or destroys entities which left the playfield, in dispatch order
+    fn cull(&mut self) {
+        let mut culled = self.scene.physics.cull();
+        {
+            let events = self.events.deref().borrow();
+            culled.sort_by_key(|&(id, _)| (events.dispatch_key(&id), id));
+        }
+        culled.dedup();
+        for (id, policy) in culled {
+            match policy {
+                scene::CullPolicy::Despawn => self.destroy(id),
+                scene::CullPolicy::Notify => self.scene.dispatch(id, Rc::new(event::Event::OutOfBounds)),
+                scene::CullPolicy::Keep => { }
+            }
+        }
+    }
+
+    /// Runs a step of a paused simulation: input is still handled but no time passes
+    fn idle_simulation(&mut self) {
+        self.dispatch_input();
+        self.handle_events();
+        self.handle_sys_events();
+    }
+
+    fn handle_sys_events(&mut self) {
         let sys_ev_queue = {
             self.events.deref().borrow_mut().flush_sys()
         };
+        let mut checkpoint = None;
         for event in sys_ev_queue {
             match event {
                 event::SysEvent::Create(f) => { self.spawn(f); },
                 event::SysEvent::Destroy(id) => self.destroy(id),
-                event::SysEvent::FastForward(amount) => {
-                    println!("Fast forwarding {:?}!", amount);
-                    let mut a = amount.clone();
-                    let step = 0.0166667f32; 
-                    while a > 0.0 {
-                        a -= step;
-                        self.advance_simulation(step);
+                event::SysEvent::StartSession(level, start_time) => self.start_session(level, start_time),
+                event::SysEvent::Seek(timeline, time) => self.seek(&timeline, time),
+                event::SysEvent::Checkpoint(time) => {
+                    if self.seeking {
+                        checkpoint = Some(time);
                     }
-                },
+                }
             }
         }
+        // Taken once the step is fully processed so entities created this step are included
+        if let Some(time) = checkpoint {
+            let snapshot = self.snapshot();
+            self.checkpoints.insert(time, snapshot);
+        }
     }
 
     fn handle_internal_event(&mut self, _event: Rc<event::Event>) {
         // Maybe do something here in the future?
     }
 }
+
+/// Physics which only keeps track of positions and never reports collisions
+#[cfg(test)]
+mod test_physics {
+    use std::rc::Rc;
+    use nalgebra::Vector2;
+    use ncollide_geometry::shape::ShapeHandle2;
+    use entity::component::PhysicsData;
+    use physics::{Collision, PhysicsEngine, QueryHit};
+    use util::{self, HashMap};
+
+    pub struct TestPhysics {
+        pos: HashMap<usize, Vector2<f32>>,
+    }
+
+    impl TestPhysics {
+        pub fn new() -> TestPhysics {
+            TestPhysics { pos: util::hashmap() }
+        }
+    }
+
+    impl PhysicsEngine<ShapeHandle2<f32>, PhysicsData> for TestPhysics {
+        fn update(&mut self, _dt: f32) -> Vec<Collision<PhysicsData>> { vec![] }
+        fn remove(&mut self, id: &usize) { self.pos.remove(id); }
+        fn add(&mut self, id: usize, pos: Vector2<f32>, _shape: ShapeHandle2<f32>, _data: Rc<PhysicsData>) { self.pos.insert(id, pos); }
+        fn get_pos(&self, id: &usize) -> Option<Vector2<f32>> { self.pos.get(id).cloned() }
+        fn set_pos(&mut self, id: &usize, pos: Vector2<f32>) { self.pos.insert(*id, pos); }
+        fn get_rot(&self, _id: &usize) -> Option<f32> { None }
+        fn set_rot(&mut self, _id: &usize, _rot: f32) { }
+        fn set_shape(&mut self, _id: &usize, _shape: ShapeHandle2<f32>) { }
+        fn raycast(&self, _o: Vector2<f32>, _d: Vector2<f32>, _m: f32, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn overlap_circle(&self, _c: Vector2<f32>, _r: f32, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn overlap_rect(&self, _c: Vector2<f32>, _h: Vector2<f32>, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn nearest(&self, _p: Vector2<f32>, _g: u8) -> Option<QueryHit<PhysicsData>> { None }
+    }
+}
+
+#[test]
+fn test_headless_tick() {
+    use std::cell::Cell;
+
+    struct Counter {
+        id: scene::Handle,
+        updates: Rc<Cell<usize>>,
+        keys: Rc<Cell<usize>>,
+        despawns: Rc<Cell<usize>>,
+    }
+
+    impl entity::Entity for Counter {
+        fn handle_event(&mut self, e: Rc<event::Event>) {
+            match *e {
+                event::Event::Update(_) => self.updates.set(self.updates.get() + 1),
+                event::Event::KeyInput(_, _) => self.keys.set(self.keys.get() + 1),
+                event::Event::Despawn => self.despawns.set(self.despawns.get() + 1),
+                _ => { }
+            }
+        }
+
+        fn id(&self) -> scene::Handle {
+            self.id
+        }
+    }
+
+    let mut engine: Engine<Counter> = Engine::headless(200.0, 700, Box::new(test_physics::TestPhysics::new()));
+    let updates = Rc::new(Cell::new(0));
+    let keys = Rc::new(Cell::new(0));
+    let despawns = Rc::new(Cell::new(0));
+    let id = engine.scene.world.registry.borrow_mut().get_handle();
+    engine.spawn_entity(Counter {
+        id: id,
+        updates: updates.clone(),
+        keys: keys.clone(),
+        despawns: despawns.clone(),
+    });
+    engine.events.borrow_mut().subscribe(id, event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::Z));
+
+    engine.tick(3, true);
+    assert_eq!(updates.get(), 3);
+
+    engine.inject(event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::Z));
+    engine.tick(0, true);
+    assert_eq!(keys.get(), 0);
+    engine.tick(1, false);
+    assert_eq!(keys.get(), 1);
+    assert_eq!(updates.get(), 4);
+
+    engine.events.borrow_mut().enqueue_specific(id, event::Event::Update(0.0));
+    engine.destroy(id);
+    engine.destroy(id);
+    assert_eq!(despawns.get(), 1);
+    engine.tick(1, false);
+    assert_eq!(updates.get(), 4);
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    use std::cell::Cell;
+
+    /// Moves one unit per second, reporting where it is after every update and restore
+    struct Mover {
+        id: scene::Handle,
+        x: f32,
+        seen: Rc<Cell<f32>>,
+    }
+
+    impl entity::Entity for Mover {
+        fn handle_event(&mut self, e: Rc<event::Event>) {
+            match *e {
+                event::Event::Update(dt) => {
+                    self.x += dt;
+                    self.seen.set(self.x);
+                }
+                event::Event::Restore => self.seen.set(self.x),
+                _ => { }
+            }
+        }
+
+        fn id(&self) -> scene::Handle {
+            self.id
+        }
+
+        fn snapshot(&self) -> Option<Box<Fn(&Engine<Mover>) -> Mover>> {
+            let (x, seen) = (self.x, self.seen.clone());
+            Some(Box::new(move |engine| {
+                Mover {
+                    id: engine.scene.world.registry.borrow_mut().get_handle(),
+                    x: x,
+                    seen: seen.clone(),
+                }
+            }))
+        }
+    }
+
+    let mut engine: Engine<Mover> = Engine::headless(200.0, 700, Box::new(test_physics::TestPhysics::new()));
+    let seen = Rc::new(Cell::new(0.0));
+    let id = engine.scene.world.registry.borrow_mut().get_handle();
+    engine.spawn_entity(Mover {
+        id: id,
+        x: 0.0,
+        seen: seen.clone(),
+    });
+
+    engine.resources.borrow_mut().keep_in_snapshots::<u32>();
+    engine.resources.borrow_mut().insert(1u32);
+    engine.tick(5, false);
+    let snapshot = engine.snapshot();
+    let at_snapshot = seen.get();
+    engine.resources.borrow_mut().insert(2u32);
+    engine.tick(5, false);
+    let later = seen.get();
+    assert!(later > at_snapshot);
+
+    engine.restore(&snapshot);
+    assert_eq!(seen.get(), at_snapshot);
+    assert_eq!(engine.resources.borrow().get::<u32>(), Some(&1));
+    assert_eq!(engine.scene.world.entities.borrow().len(), 1);
+    assert!(!engine.scene.world.contains(&id));
+    engine.tick(5, false);
+    assert_eq!(seen.get(), later);
+}
+
+#[test]
+fn test_cull_policies() {
+    use std::cell::Cell;
+    use nalgebra::Vector2;
+    use ncollide_geometry::shape::{Ball, ShapeHandle2};
+    use entity::component::PhysicsData;
+
+    struct Watched {
+        id: scene::Handle,
+        despawns: Rc<Cell<usize>>,
+        left: Rc<Cell<usize>>,
+    }
+
+    impl entity::Entity for Watched {
+        fn handle_event(&mut self, e: Rc<event::Event>) {
+            match *e {
+                event::Event::Despawn => self.despawns.set(self.despawns.get() + 1),
+                event::Event::OutOfBounds => self.left.set(self.left.get() + 1),
+                _ => { }
+            }
+        }
+
+        fn id(&self) -> scene::Handle {
+            self.id
+        }
+    }
+
+    let mut engine: Engine<Watched> = Engine::headless(200.0, 700, Box::new(test_physics::TestPhysics::new()));
+    let despawns = Rc::new(Cell::new(0));
+    let left = Rc::new(Cell::new(0));
+    let (despawned, despawn_box, notify_box) = {
+        let watch = |policy: scene::CullPolicy| {
+            let id = engine.scene.world.registry.borrow_mut().get_handle();
+            engine.spawn_entity(Watched {
+                id: id,
+                despawns: despawns.clone(),
+                left: left.clone(),
+            });
+            let physics = &engine.scene.physics;
+            let hitbox = physics.add(Vector2::new(0.0, 0.0), ShapeHandle2::new(Ball::new(1.0)), Rc::new(PhysicsData::new(id, 0, 1)));
+            physics.set_cull(&hitbox, id, policy);
+            (id, hitbox)
+        };
+        let (despawned, despawn_box) = watch(scene::CullPolicy::Despawn);
+        let (_, notify_box) = watch(scene::CullPolicy::Notify);
+        (despawned, despawn_box, notify_box)
+    };
+    let outside = Vector2::new(0.0, 1000.0);
+    let inside = Vector2::new(0.0, 0.0);
+
+    engine.tick(1, false);
+    assert_eq!((despawns.get(), left.get()), (0, 0));
+
+    engine.scene.physics.set_pos(&despawn_box, outside);
+    engine.scene.physics.set_pos(&notify_box, outside);
+    engine.tick(1, false);
+    assert_eq!((despawns.get(), left.get()), (1, 1));
+    assert!(!engine.scene.world.contains(&despawned));
+
+    // Staying outside doesn't count as leaving again
+    engine.tick(2, false);
+    assert_eq!((despawns.get(), left.get()), (1, 1));
+
+    engine.scene.physics.set_pos(&despawn_box, inside);
+    engine.scene.physics.set_pos(&notify_box, inside);
+    engine.tick(1, false);
+    assert_eq!((despawns.get(), left.get()), (1, 1));
+    engine.scene.physics.set_pos(&despawn_box, outside);
+    engine.scene.physics.set_pos(&notify_box, outside);
+    engine.tick(1, false);
+    assert_eq!((despawns.get(), left.get()), (1, 2));
+}
+
+#[test]
+fn test_recording_withholds_unlisted_keys() {
+    use std::cell::Cell;
+
+    struct Listener {
+        id: scene::Handle,
+        keys: Rc<Cell<usize>>,
+    }
+
+    impl entity::Entity for Listener {
+        fn handle_event(&mut self, e: Rc<event::Event>) {
+            if let event::Event::KeyInput(_, _) = *e {
+                self.keys.set(self.keys.get() + 1);
+            }
+        }
+
+        fn id(&self) -> scene::Handle {
+            self.id
+        }
+    }
+
+    let mut engine: Engine<Listener> = Engine::headless(200.0, 700, Box::new(test_physics::TestPhysics::new()));
+    let keys = Rc::new(Cell::new(0));
+    let id = engine.scene.world.registry.borrow_mut().get_handle();
+    engine.spawn_entity(Listener {
+        id: id,
+        keys: keys.clone(),
+    });
+    engine.events.borrow_mut().subscribe(id, event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::Z));
+    engine.start_session(String::from("level.toml"), 0.0);
+
+    engine.inject(event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::Z));
+    engine.inject(event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::F1));
+    engine.tick(1, false);
+    assert_eq!(keys.get(), 1);
+
+    let recording = engine.take_recording().unwrap();
+    assert_eq!(recording.inputs.len(), 1);
+    assert_eq!(recording.inputs[0].key, event::KeyCode::Z);
+    // Unlisted keys reach the game again once nothing is recorded
+    engine.inject(event::Event::KeyInput(event::InputState::Pressed, event::KeyCode::F1));
+    engine.tick(1, false);
+    assert_eq!(keys.get(), 2);
+}
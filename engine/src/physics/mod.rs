@@ -1,18 +1,85 @@
 use nalgebra::Vector2;
+use ncollide_geometry::query::Proximity;
 use std::rc::Rc;
 
+use scene::Handle;
+
 pub trait PhysicsEngine<S, D> {
     fn update(&mut self, dt: f32) -> Vec<Collision<D>>;
     fn remove(&mut self, id: &usize);
     fn add(&mut self, id: usize, pos: Vector2<f32>, shape: S, data: Rc<D>);
     fn get_pos(&self, id: &usize) -> Option<Vector2<f32>>;
     fn set_pos(&mut self, id: &usize, pos: Vector2<f32>);
+    /// Moves the object without travelling the distance in between, so nothing on the way is hit
+    fn teleport(&mut self, id: &usize, pos: Vector2<f32>) {
+        self.set_pos(id, pos);
+    }
+    /// Moves and rotates many objects at once, as their id, position and rotation
+    fn set_positions(&mut self, moves: &[(usize, Vector2<f32>, f32)]) {
+        for &(id, pos, rot) in moves {
+            self.set_pos(&id, pos);
+            self.set_rot(&id, rot);
+        }
+    }
+    /// Orientation of the object in radians, counter clockwise
+    fn get_rot(&self, id: &usize) -> Option<f32>;
+    fn set_rot(&mut self, id: &usize, rot: f32);
+    /// Replaces the hitbox of the object, e.g. for lasers changing length
+    fn set_shape(&mut self, id: &usize, shape: S);
+    /// Objects of the given groups hit by a ray, closest first. A zero direction hits nothing
+    fn raycast(&self, origin: Vector2<f32>, dir: Vector2<f32>, max_dist: f32, groups: &[u8]) -> Vec<QueryHit<D>>;
+    fn overlap_circle(&self, center: Vector2<f32>, radius: f32, groups: &[u8]) -> Vec<QueryHit<D>>;
+    fn overlap_rect(&self, center: Vector2<f32>, half_extents: Vector2<f32>, groups: &[u8]) -> Vec<QueryHit<D>>;
+    fn nearest(&self, point: Vector2<f32>, group: u8) -> Option<QueryHit<D>>;
 }
 
+/// Object found by a spatial query
+#[derive(Clone)]
+pub struct QueryHit<D> {
+    pub id: Handle,
+    pub data: Rc<D>,
+    /// Distance along the ray or to the queried point, 0 for overlaps
+    pub distance: f32,
+}
+
+/// Contact between two objects, `Intersecting` for an actual hit or `WithinMargin`
+/// when they only came close (e.g. a graze)
 #[derive(Clone)]
 pub struct Collision<D> {
-    pub id1: usize,
-    pub id2: usize,
+    pub id1: Handle,
+    pub id2: Handle,
     pub data1: Rc<D>,
     pub data2: Rc<D>,
+    pub proximity: Proximity,
+    /// Normal points from the first object towards the second
+    pub contact: Contact,
+}
+
+/// Whether a pair of objects started touching this step, kept touching or stopped
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContactPhase {
+    Begin,
+    Persist,
+    End,
+}
+
+/// Where two objects touch, in world coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub point: Vector2<f32>,
+    /// Unit vector pointing from this object towards the other one
+    pub normal: Vector2<f32>,
+    /// Penetration depth, negative if the objects are only within the proximity margin
+    pub depth: f32,
+}
+
+impl Contact {
+    /// The same contact seen from the other object
+    pub fn flipped(&self) -> Contact {
+        Contact {
+            point: self.point,
+            normal: self.normal * -1.0,
+            depth: self.depth,
+        }
+    }
 }
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use event::{InputState, KeyCode};
+
+const HEADER: &'static str = "nishikaku replay 1";
+
+/// A recorded play session, every key input is tagged with the simulation tick
+/// (relative to the start of the session) it was dispatched on
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub level: String,
+    pub start_time: f32,
+    pub inputs: Vec<ReplayInput>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub state: InputState,
+    pub key: KeyCode,
+}
+
+impl Replay {
+    pub fn new(level: String, start_time: f32) -> Replay {
+        Replay {
+            level: level,
+            start_time: start_time,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let mut f = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+        let mut s = String::new();
+        match f.read_to_string(&mut s) {
+            Ok(_) => { },
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+        Replay::parse(&s)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut f = match File::create(path) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+        match f.write_all(self.serialize().as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!("{:?}", e)),
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut s = String::new();
+        s.push_str(HEADER);
+        s.push_str(&format!("\nlevel {}\nstart_time {}\n", self.level, self.start_time));
+        for input in self.inputs.iter() {
+            let state = match input.state {
+                InputState::Pressed => "pressed",
+                InputState::Released => "released",
+            };
+            // Keys without a name are withheld while recording, so this can't fail
+            s.push_str(&format!("{} {} {}\n", input.tick, state, key_name(&input.key).unwrap()));
+        }
+        s
+    }
+
+    pub fn parse(s: &str) -> Result<Replay, String> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(String::from("File is not a replay or has an unsupported version"));
+        }
+        let level = match lines.next() {
+            Some(l) if l.starts_with("level ") => String::from(&l[6..]),
+            l => return Err(format!("Expected level path, found {:?}", l)),
+        };
+        let start_time = match lines.next() {
+            Some(l) if l.starts_with("start_time ") => {
+                match l[11..].parse::<f32>() {
+                    Ok(t) => t,
+                    Err(_) => return Err(format!("Invalid start time {:?}", l)),
+                }
+            }
+            l => return Err(format!("Expected start time, found {:?}", l)),
+        };
+
+        let mut replay = Replay::new(level, start_time);
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+            let parts: Vec<_> = line.split(' ').collect();
+            if parts.len() != 3 {
+                return Err(format!("Invalid input line {:?}", line));
+            }
+            let tick = match parts[0].parse::<u64>() {
+                Ok(t) => t,
+                Err(_) => return Err(format!("Invalid tick in {:?}", line)),
+            };
+            let state = match parts[1] {
+                "pressed" => InputState::Pressed,
+                "released" => InputState::Released,
+                s => return Err(format!("Invalid input state {:?}", s)),
+            };
+            let key = match key_code(parts[2]) {
+                Some(k) => k,
+                None => return Err(format!("Unknown key {:?}", parts[2])),
+            };
+            replay.inputs.push(ReplayInput {
+                tick: tick,
+                state: state,
+                key: key,
+            });
+        }
+        Ok(replay)
+    }
+}
+
+static KEYS: &'static [(KeyCode, &'static str)] = &[
+    (KeyCode::Up, "Up"), (KeyCode::Down, "Down"), (KeyCode::Left, "Left"), (KeyCode::Right, "Right"),
+    (KeyCode::LShift, "LShift"), (KeyCode::RShift, "RShift"),
+    (KeyCode::LControl, "LControl"), (KeyCode::RControl, "RControl"),
+    (KeyCode::Space, "Space"), (KeyCode::Return, "Return"), (KeyCode::Escape, "Escape"),
+    (KeyCode::A, "A"), (KeyCode::B, "B"), (KeyCode::C, "C"), (KeyCode::D, "D"),
+    (KeyCode::E, "E"), (KeyCode::F, "F"), (KeyCode::G, "G"), (KeyCode::H, "H"),
+    (KeyCode::I, "I"), (KeyCode::J, "J"), (KeyCode::K, "K"), (KeyCode::L, "L"),
+    (KeyCode::M, "M"), (KeyCode::N, "N"), (KeyCode::O, "O"), (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"), (KeyCode::R, "R"), (KeyCode::S, "S"), (KeyCode::T, "T"),
+    (KeyCode::U, "U"), (KeyCode::V, "V"), (KeyCode::W, "W"), (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"), (KeyCode::Z, "Z"),
+];
+
+/// Name a key is stored under in replay files, None if the key can't be recorded
+pub fn key_name(key: &KeyCode) -> Option<&'static str> {
+    KEYS.iter().find(|&&(ref k, _)| k == key).map(|&(_, name)| name)
+}
+
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    KEYS.iter().find(|&&(_, n)| n == name).map(|&(k, _)| k)
+}
+
+#[test]
+fn test_replay_roundtrip() {
+    let mut replay = Replay::new(String::from("assets/levels/level.toml"), 12.5);
+    replay.inputs.push(ReplayInput { tick: 0, state: InputState::Pressed, key: KeyCode::Z });
+    replay.inputs.push(ReplayInput { tick: 42, state: InputState::Released, key: KeyCode::LShift });
+    let parsed = Replay::parse(&replay.serialize()).unwrap();
+    assert_eq!(parsed.level, replay.level);
+    assert_eq!(parsed.start_time, 12.5);
+    assert_eq!(parsed.inputs.len(), 2);
+    assert_eq!(parsed.inputs[1].tick, 42);
+    assert_eq!(parsed.inputs[1].key, KeyCode::LShift);
+}
@@ -0,0 +1,128 @@
+use std::any::{Any, TypeId};
+
+use util::{self, HashMap};
+
+/// Engine wide singletons keyed by their type, used for state shared between entities
+pub struct Resources {
+    values: HashMap<TypeId, Box<Any>>,
+    /// Copies the resources which are kept in snapshots
+    cloners: HashMap<TypeId, fn(&Any) -> Box<Any>>,
+}
+
+fn clone_value<T: Any + Clone>(value: &Any) -> Box<Any> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+impl Resources {
+    pub fn new() -> Resources {
+        Resources {
+            values: util::hashmap(),
+            cloners: util::hashmap(),
+        }
+    }
+
+    /// Makes snapshots keep the resource of type `T`. Resources derived from the live
+    /// entities are better left out, restored entities rebuild them
+    pub fn keep_in_snapshots<T: Any + Clone>(&mut self) {
+        self.cloners.insert(TypeId::of::<T>(), clone_value::<T>);
+    }
+
+    /// Copy of every resource kept in snapshots
+    pub fn snapshot(&self) -> Resources {
+        let mut saved = Resources::new();
+        for (id, clone) in self.cloners.iter() {
+            if let Some(value) = self.values.get(id) {
+                saved.values.insert(*id, clone(&**value));
+                saved.cloners.insert(*id, *clone);
+            }
+        }
+        saved
+    }
+
+    /// Puts back the resources kept in snapshots as they were in `saved`,
+    /// the ones `saved` doesn't have are removed
+    pub fn restore(&mut self, saved: &Resources) {
+        for (id, clone) in self.cloners.iter() {
+            match saved.values.get(id) {
+                Some(value) => {
+                    self.values.insert(*id, clone(&**value));
+                }
+                None => {
+                    self.values.remove(id);
+                }
+            }
+        }
+    }
+
+    /// Stores `value`, replacing any resource of the same type
+    pub fn insert<T: Any>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        match self.values.remove(&TypeId::of::<T>()) {
+            Some(value) => value.downcast::<T>().ok().map(|v| *v),
+            None => None,
+        }
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>()).and_then(|v| v.downcast_ref::<T>())
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.values.get_mut(&TypeId::of::<T>()).and_then(|v| v.downcast_mut::<T>())
+    }
+
+    /// Returns the resource of type `T`, inserting its default value first if there is none
+    pub fn entry<T: Any + Default>(&mut self) -> &mut T {
+        self.values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut::<T>()
+            .unwrap()
+    }
+}
+
+#[test]
+fn test_resources() {
+    #[derive(Default)]
+    struct Count(usize);
+
+    let mut res = Resources::new();
+    assert!(res.get::<Count>().is_none());
+    res.entry::<Count>().0 += 2;
+    res.entry::<Count>().0 += 1;
+    assert_eq!(res.get::<Count>().unwrap().0, 3);
+    res.insert(String::from("level"));
+    assert_eq!(res.remove::<String>(), Some(String::from("level")));
+    assert!(!res.contains::<String>());
+}
+
+#[test]
+fn test_resource_snapshot() {
+    #[derive(Clone, Default)]
+    struct Count(usize);
+    #[derive(Clone, Default)]
+    struct Total(usize);
+
+    let mut res = Resources::new();
+    res.keep_in_snapshots::<Count>();
+    res.keep_in_snapshots::<Total>();
+    res.insert(Count(1));
+    res.insert(String::from("level"));
+    let saved = res.snapshot();
+    res.entry::<Count>().0 = 5;
+    res.insert(Total(3));
+    res.insert(String::from("menu"));
+    res.restore(&saved);
+    assert_eq!(res.get::<Count>().unwrap().0, 1);
+    // Missing from the snapshot, so it didn't exist yet
+    assert!(!res.contains::<Total>());
+    // Not kept in snapshots
+    assert_eq!(res.get::<String>().unwrap(), "menu");
+}
@@ -1,18 +1,28 @@
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use ncollide_geometry::shape::ShapeHandle2;
+use ncollide_geometry::query::Proximity;
 use nalgebra::{Vector2};
 
 use event::{Event, CollisionData};
 use entity::Entity;
 use entity::component::PhysicsData;
 use util::{self, HashMap, HashSet};
-use physics::{Collision, PhysicsEngine};
+use physics::{Collision, ContactPhase, PhysicsEngine, QueryHit};
+
+/// Generational reference to an entity, a handle to a destroyed entity
+/// never resolves to whichever entity reuses its index
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle {
+    pub index: usize,
+    pub generation: u32,
+}
 
 pub struct World<E: Entity> {
-    pub entities: RefCell<HashMap<usize, RefCell<E>>>,
+    pub entities: RefCell<HashMap<Handle, RefCell<E>>>,
     pub registry: RefCell<Registry>,
 }
 
@@ -33,9 +43,51 @@ impl<E: Entity> Default for World<E> {
 
 pub type PhysicsHandler = Box<PhysicsEngine<ShapeHandle2<f32>, PhysicsData>>;
 
+/// Both entities and hitbox tags of a touching pair, lowest first
+type ContactKey = (Handle, usize, Handle, usize);
+
+/// Default distance objects may travel past the edge of the screen before being culled
+const PLAYFIELD_MARGIN: f32 = 50.0;
+
+/// What happens to an entity once one of its hitboxes leaves the playfield
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CullPolicy {
+    Keep,
+    /// The entity is sent `Event::OutOfBounds` each time it leaves
+    Notify,
+    /// The entity is destroyed
+    Despawn,
+}
+
+/// Area objects are allowed in, in world units, edges are extended by the margin
+#[derive(Copy, Clone, Debug)]
+pub struct Playfield {
+    pub mins: Vector2<f32>,
+    pub maxs: Vector2<f32>,
+    pub margin: f32,
+}
+
+impl Playfield {
+    pub fn contains(&self, pos: &Vector2<f32>) -> bool {
+        pos.x >= self.mins.x - self.margin && pos.x <= self.maxs.x + self.margin &&
+            pos.y >= self.mins.y - self.margin && pos.y <= self.maxs.y + self.margin
+    }
+}
+
+struct Culled {
+    entity: Handle,
+    policy: CullPolicy,
+    outside: bool,
+}
+
 pub struct PhysicsWorld {
     registry: RefCell<Registry>,
     engine: RefCell<Box<PhysicsEngine<ShapeHandle2<f32>, PhysicsData>>>,
+    /// Pairs touching during the last update along with their last collision
+    contacts: RefCell<HashMap<ContactKey, Collision<PhysicsData>>>,
+    playfield: Cell<Playfield>,
+    /// Hitboxes with a cull policy, ordered so culling is deterministic
+    culled: RefCell<BTreeMap<Handle, Culled>>,
     pub scaler: f32,
 }
 
@@ -44,43 +96,192 @@ impl PhysicsWorld {
         PhysicsWorld {
             engine: RefCell::new(engine),
             registry: RefCell::new(Registry::new()),
+            contacts: RefCell::new(util::hashmap()),
+            playfield: Cell::new(Playfield {
+                mins: Vector2::new(-scaler, -scaler),
+                maxs: Vector2::new(scaler, scaler),
+                margin: PLAYFIELD_MARGIN,
+            }),
+            culled: RefCell::new(BTreeMap::new()),
             scaler: scaler,
         }
     }
 
-    pub fn update(&self, dt: f32) -> Vec<Collision<PhysicsData>> {
+    /// Steps the physics engine and sorts its collisions into contacts which began,
+    /// persisted or ended. Proximity reports aren't tracked and always count as a begin
+    pub fn update(&self, dt: f32) -> Vec<(ContactPhase, Collision<PhysicsData>)> {
         self.registry.borrow_mut().reclaim();
-        self.engine.borrow_mut().update(dt)
+        let found = self.engine.borrow_mut().update(dt);
+        let mut previous = self.contacts.borrow_mut();
+        let mut current = util::hashmap();
+        let mut res = Vec::with_capacity(found.len());
+        for collision in found {
+            if collision.proximity != Proximity::Intersecting {
+                res.push((ContactPhase::Begin, collision));
+                continue;
+            }
+            let key = contact_key(&collision);
+            if current.contains_key(&key) {
+                continue;
+            }
+            let phase = match previous.remove(&key) {
+                Some(_) => ContactPhase::Persist,
+                None => ContactPhase::Begin,
+            };
+            current.insert(key, collision.clone());
+            res.push((phase, collision));
+        }
+        for (_, collision) in previous.drain() {
+            res.push((ContactPhase::End, collision));
+        }
+        *previous = current;
+        res
+    }
+
+    pub fn playfield(&self) -> Playfield {
+        self.playfield.get()
     }
 
-    pub fn remove(&self, id: &usize) {
-        self.engine.borrow_mut().remove(id);
-        self.registry.borrow_mut().return_id(*id);
+    /// Defaults to the screen with a margin of `PLAYFIELD_MARGIN`
+    pub fn set_playfield(&self, playfield: Playfield) {
+        self.playfield.set(playfield);
     }
 
-    pub fn add(&self, pos: Vector2<f32>, shape: ShapeHandle2<f32>, data: Rc<PhysicsData>) -> usize {
-        let id = self.registry.borrow_mut().get_id();
-        self.engine.borrow_mut().add(id, pos, shape, data);
+    pub fn set_cull(&self, id: &Handle, entity: Handle, policy: CullPolicy) {
+        if !self.registry.borrow().is_live(id) {
+            return;
+        }
+        let mut culled = self.culled.borrow_mut();
+        match policy {
+            CullPolicy::Keep => {
+                culled.remove(id);
+            }
+            _ => {
+                culled.insert(*id, Culled {
+                    entity: entity,
+                    policy: policy,
+                    outside: false,
+                });
+            }
+        }
+    }
+
+    /// Entities which had a hitbox leave the playfield since the last call, with their policy
+    pub fn cull(&self) -> Vec<(Handle, CullPolicy)> {
+        let playfield = self.playfield.get();
+        let engine = self.engine.borrow();
+        let mut res = Vec::new();
+        for (id, culled) in self.culled.borrow_mut().iter_mut() {
+            let inside = match engine.get_pos(&id.index) {
+                Some(pos) => playfield.contains(&pos),
+                None => true,
+            };
+            if !inside && !culled.outside {
+                res.push((culled.entity, culled.policy));
+            }
+            culled.outside = !inside;
+        }
+        res
+    }
+
+    pub fn remove(&self, id: &Handle) {
+        self.culled.borrow_mut().remove(id);
+        if self.registry.borrow_mut().return_handle(*id) {
+            self.engine.borrow_mut().remove(&id.index);
+        }
+    }
+
+    pub fn add(&self, pos: Vector2<f32>, shape: ShapeHandle2<f32>, data: Rc<PhysicsData>) -> Handle {
+        let id = self.registry.borrow_mut().get_handle();
+        self.engine.borrow_mut().add(id.index, pos, shape, data);
         id
     }
 
-    pub fn get_pos(&self, id: &usize) -> Option<Vector2<f32>> {
-        self.engine.borrow_mut().get_pos(id)
+    pub fn get_pos(&self, id: &Handle) -> Option<Vector2<f32>> {
+        if !self.registry.borrow().is_live(id) {
+            return None;
+        }
+        self.engine.borrow_mut().get_pos(&id.index)
+    }
+
+    pub fn set_pos(&self, id: &Handle, pos: Vector2<f32>) {
+        if self.registry.borrow().is_live(id) {
+            self.engine.borrow_mut().set_pos(&id.index, pos)
+        }
+    }
+
+    /// Moves and rotates many hitboxes through a single call to the engine, stale ones are skipped
+    pub fn set_positions(&self, moves: &[(Handle, Vector2<f32>, f32)]) {
+        let live: Vec<_> = {
+            let registry = self.registry.borrow();
+            moves.iter()
+                .filter(|&&(id, _, _)| registry.is_live(&id))
+                .map(|&(id, pos, rot)| (id.index, pos, rot))
+                .collect()
+        };
+        self.engine.borrow_mut().set_positions(&live);
     }
 
-    pub fn set_pos(&self, id: &usize, pos: Vector2<f32>) {
-        self.engine.borrow_mut().set_pos(id, pos)
+    pub fn teleport(&self, id: &Handle, pos: Vector2<f32>) {
+        if self.registry.borrow().is_live(id) {
+            self.engine.borrow_mut().teleport(&id.index, pos)
+        }
+    }
+
+    pub fn get_rot(&self, id: &Handle) -> Option<f32> {
+        if !self.registry.borrow().is_live(id) {
+            return None;
+        }
+        self.engine.borrow().get_rot(&id.index)
+    }
+
+    pub fn set_rot(&self, id: &Handle, rot: f32) {
+        if self.registry.borrow().is_live(id) {
+            self.engine.borrow_mut().set_rot(&id.index, rot)
+        }
+    }
+
+    pub fn set_shape(&self, id: &Handle, shape: ShapeHandle2<f32>) {
+        if self.registry.borrow().is_live(id) {
+            self.engine.borrow_mut().set_shape(&id.index, shape)
+        }
+    }
+
+    pub fn raycast(&self, origin: Vector2<f32>, dir: Vector2<f32>, max_dist: f32, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        self.engine.borrow().raycast(origin, dir, max_dist, groups)
+    }
+
+    pub fn overlap_circle(&self, center: Vector2<f32>, radius: f32, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        self.engine.borrow().overlap_circle(center, radius, groups)
+    }
+
+    pub fn overlap_rect(&self, center: Vector2<f32>, half_extents: Vector2<f32>, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        self.engine.borrow().overlap_rect(center, half_extents, groups)
+    }
+
+    pub fn nearest(&self, point: Vector2<f32>, group: u8) -> Option<QueryHit<PhysicsData>> {
+        self.engine.borrow().nearest(point, group)
+    }
+}
+
+fn contact_key(collision: &Collision<PhysicsData>) -> ContactKey {
+    let a = (collision.id1, collision.data1.tag);
+    let b = (collision.id2, collision.data2.tag);
+    if a <= b {
+        (a.0, a.1, b.0, b.1)
+    } else {
+        (b.0, b.1, a.0, a.1)
     }
 }
 
 pub struct EntityAccessor<'a, E: 'a + Entity> {
-    id: usize,
-    map_ref: Ref<'a, HashMap<usize, RefCell<E>>>,
+    id: Handle,
+    map_ref: Ref<'a, HashMap<Handle, RefCell<E>>>,
 }
 
 pub struct MutEntityAccessor<'a, E: 'a + Entity> {
-    id: usize,
-    map_ref: Ref<'a, HashMap<usize, RefCell<E>>>,
+    id: Handle,
+    map_ref: Ref<'a, HashMap<Handle, RefCell<E>>>,
 }
 
 impl<'a, E: Entity> EntityAccessor<'a, E> {
@@ -102,15 +303,19 @@ impl<'a, E: Entity> MutEntityAccessor<'a, E> {
 }
 
 impl<E: Entity> World<E> {
-    pub fn insert(&self, id: usize, e: E) {
+    pub fn insert(&self, id: Handle, e: E) {
         self.entities.borrow_mut().insert(id, RefCell::new(e));
     }
 
-    pub fn remove(&self, id: &usize) {
+    pub fn remove(&self, id: &Handle) {
         self.entities.borrow_mut().remove(id);
     }
 
-    pub fn get_entity(&self, id: &usize) -> EntityAccessor<E> {
+    pub fn contains(&self, id: &Handle) -> bool {
+        self.entities.borrow().contains_key(id)
+    }
+
+    pub fn get_entity(&self, id: &Handle) -> EntityAccessor<E> {
         let r = self.entities.borrow();
         EntityAccessor {
             id: id.clone(),
@@ -118,7 +323,7 @@ impl<E: Entity> World<E> {
         }
     }
 
-    pub fn get_entity_mut(&self, id: &usize) -> MutEntityAccessor<E> {
+    pub fn get_entity_mut(&self, id: &Handle) -> MutEntityAccessor<E> {
         let r = self.entities.borrow();
         MutEntityAccessor {
             id: id.clone(),
@@ -127,15 +332,16 @@ impl<E: Entity> World<E> {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Registry {
     counter: usize,
     free: Vec<usize>,
     reclaimed: Vec<usize>,
     reclaimable: bool,
-    names: HashMap<String, usize>,
-    rev_names: HashMap<usize, String>,
-    tags: HashMap<String, HashSet<usize>>,
+    generations: HashMap<usize, u32>,
+    names: HashMap<String, Handle>,
+    rev_names: HashMap<Handle, String>,
+    tags: HashMap<String, HashSet<Handle>>,
 }
 
 impl Registry {
@@ -145,6 +351,7 @@ impl Registry {
             free: vec![],
             reclaimed: vec![],
             reclaimable: true,
+            generations: util::hashmap(),
             names: util::hashmap(),
             rev_names: util::hashmap(),
             tags: util::hashmap(),
@@ -156,7 +363,7 @@ impl Registry {
         self.reclaimable = false;
     }
 
-    pub fn tag_id(&mut self, id: usize, tag: String) {
+    pub fn tag_id(&mut self, id: Handle, tag: String) {
         if self.tags.contains_key(&tag) {
             self.tags.get_mut(&tag).unwrap().insert(id);
         } else {
@@ -166,30 +373,30 @@ impl Registry {
         }
     }
 
-    pub fn untag_id(&mut self, id: &usize, tag: &String) {
+    pub fn untag_id(&mut self, id: &Handle, tag: &String) {
         if self.tags.contains_key(tag) {
             self.tags.get_mut(tag).unwrap().remove(id);
         }
     }
 
-    pub fn get_tagged(&mut self, tag: &String)  -> Option<&mut HashSet<usize>> {
+    pub fn get_tagged(&mut self, tag: &String)  -> Option<&mut HashSet<Handle>> {
         self.tags.get_mut(tag)
     }
 
-    pub fn remove_tagged(&mut self, tag: &String)  -> Option<HashSet<usize>> {
+    pub fn remove_tagged(&mut self, tag: &String)  -> Option<HashSet<Handle>> {
         self.tags.remove(tag)
     }
 
-    pub fn create_alias(&mut self, name: String, id: usize) {
+    pub fn create_alias(&mut self, name: String, id: Handle) {
         self.names.insert(name.clone(), id.clone());
         self.rev_names.insert(id, name);
     }
 
-    pub fn get_aliased_id(&mut self, name: &String) -> Option<&usize> {
+    pub fn get_aliased_id(&mut self, name: &String) -> Option<&Handle> {
         self.names.get(name)
     }
 
-    pub fn get_aliased_string(&mut self, id: &usize) -> Option<&String> {
+    pub fn get_aliased_string(&mut self, id: &Handle) -> Option<&String> {
         self.rev_names.get(id)
     }
 
@@ -220,6 +427,30 @@ impl Registry {
     pub fn reclaim(&mut self) {
         self.free.append(&mut self.reclaimed);
     }
+
+    /// Hands out an id along with the current generation of its index
+    pub fn get_handle(&mut self) -> Handle {
+        let index = self.get_id();
+        Handle {
+            index: index,
+            generation: *self.generations.get(&index).unwrap_or(&0),
+        }
+    }
+
+    /// Invalidates the handle and frees its index, returns false if it was already stale
+    pub fn return_handle(&mut self, handle: Handle) -> bool {
+        if !self.is_live(&handle) {
+            return false;
+        }
+        self.generations.insert(handle.index, handle.generation.wrapping_add(1));
+        self.return_id(handle.index);
+        true
+    }
+
+    pub fn is_live(&self, handle: &Handle) -> bool {
+        handle.index != 0 && handle.index < self.counter &&
+            *self.generations.get(&handle.index).unwrap_or(&0) == handle.generation
+    }
 }
 
 pub struct Scene<E: Entity> {
@@ -235,7 +466,7 @@ impl<E: Entity> Scene<E> {
         }
     }
 
-    pub fn dispatch(&self, id: usize, ev: Rc<Event>) {
+    pub fn dispatch(&self, id: Handle, ev: Rc<Event>) {
         let acc = self.world.deref().get_entity_mut(&id);
         match acc.access() {
             Some(mut e) => e.deref_mut().handle_event(ev),
@@ -243,19 +474,125 @@ impl<E: Entity> Scene<E> {
         };
     }
 
-    pub fn update(&self, dt: f32) {
+    pub fn update(&self, dt: f32) -> Vec<(ContactPhase, Collision<PhysicsData>)> {
         self.world.update();
-        // TODO: Dispatch
-        let collisions = self.physics.update(dt);
-        for collision in collisions {
-            self.dispatch(collision.id2, Rc::new(Event::Collision(collision.id1, CollisionData {
-                this_object: collision.data2.clone(),
-                other_object: collision.data1.clone(),
-            })));
-            self.dispatch(collision.id1, Rc::new(Event::Collision(collision.id2, CollisionData {
-                this_object: collision.data1.clone(),
-                other_object: collision.data2.clone(),
-            })));
+        self.physics.update(dt)
+    }
+
+    pub fn dispatch_collisions(&self, collisions: Vec<(ContactPhase, Collision<PhysicsData>)>) {
+        for (phase, collision) in collisions {
+            let event: fn(Handle, CollisionData) -> Event = match (phase, collision.proximity) {
+                (_, Proximity::WithinMargin) => Event::Proximity,
+                (ContactPhase::Begin, _) => Event::CollisionBegin,
+                (ContactPhase::Persist, _) => Event::Collision,
+                (ContactPhase::End, _) => Event::CollisionEnd,
+            };
+            // Contacts also end when one of the entities is gone, only the other one is told
+            if self.world.contains(&collision.id2) {
+                self.dispatch(collision.id2, Rc::new(event(collision.id1, CollisionData {
+                    this_object: collision.data2.clone(),
+                    other_object: collision.data1.clone(),
+                    contact: collision.contact.flipped(),
+                })));
+            }
+            if self.world.contains(&collision.id1) {
+                self.dispatch(collision.id1, Rc::new(event(collision.id2, CollisionData {
+                    this_object: collision.data1.clone(),
+                    other_object: collision.data2.clone(),
+                    contact: collision.contact,
+                })));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_stale_handle() {
+    let mut reg = Registry::new();
+    let first = reg.get_handle();
+    assert!(reg.is_live(&first));
+    assert!(reg.return_handle(first));
+    assert!(!reg.return_handle(first));
+    reg.reclaim();
+    let second = reg.get_handle();
+    assert_eq!(first.index, second.index);
+    assert!(!reg.is_live(&first));
+    assert!(reg.is_live(&second));
+}
+
+/// Physics reporting a hit between a hitbox of entity 1 and entity 2 for every tag set
+#[cfg(test)]
+mod scripted {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use ncollide_geometry::shape::ShapeHandle2;
+    use ncollide_geometry::query::Proximity;
+    use nalgebra::Vector2;
+    use entity::component::PhysicsData;
+    use physics::{Collision, Contact, PhysicsEngine, QueryHit};
+    use super::Handle;
+
+    pub struct Scripted {
+        pub touching: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl PhysicsEngine<ShapeHandle2<f32>, PhysicsData> for Scripted {
+        fn update(&mut self, _dt: f32) -> Vec<Collision<PhysicsData>> {
+            let (a, b) = (Handle { index: 1, generation: 0 }, Handle { index: 2, generation: 0 });
+            self.touching.borrow().iter().map(|&tag| {
+                Collision {
+                    id1: a,
+                    id2: b,
+                    data1: Rc::new(PhysicsData::new(a, tag, 1)),
+                    data2: Rc::new(PhysicsData::new(b, 0, 2)),
+                    proximity: Proximity::Intersecting,
+                    contact: Contact { point: Vector2::new(0.0, 0.0), normal: Vector2::new(0.0, 1.0), depth: 1.0 },
+                }
+            }).collect()
         }
+        fn remove(&mut self, _id: &usize) { }
+        fn add(&mut self, _id: usize, _pos: Vector2<f32>, _shape: ShapeHandle2<f32>, _data: Rc<PhysicsData>) { }
+        fn get_pos(&self, _id: &usize) -> Option<Vector2<f32>> { None }
+        fn set_pos(&mut self, _id: &usize, _pos: Vector2<f32>) { }
+        fn get_rot(&self, _id: &usize) -> Option<f32> { None }
+        fn set_rot(&mut self, _id: &usize, _rot: f32) { }
+        fn set_shape(&mut self, _id: &usize, _shape: ShapeHandle2<f32>) { }
+        fn raycast(&self, _o: Vector2<f32>, _d: Vector2<f32>, _m: f32, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn overlap_circle(&self, _c: Vector2<f32>, _r: f32, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn overlap_rect(&self, _c: Vector2<f32>, _h: Vector2<f32>, _g: &[u8]) -> Vec<QueryHit<PhysicsData>> { vec![] }
+        fn nearest(&self, _p: Vector2<f32>, _g: u8) -> Option<QueryHit<PhysicsData>> { None }
+    }
+}
+
+#[test]
+fn test_contact_phases() {
+    fn phases(world: &PhysicsWorld) -> Vec<ContactPhase> {
+        world.update(0.1).iter().map(|&(phase, _)| phase).collect()
     }
+
+    let touching = Rc::new(RefCell::new(vec![0]));
+    let world = PhysicsWorld::new(1.0, Box::new(scripted::Scripted { touching: touching.clone() }));
+    assert_eq!(phases(&world), vec![ContactPhase::Begin]);
+    assert_eq!(phases(&world), vec![ContactPhase::Persist]);
+    touching.borrow_mut().clear();
+    assert_eq!(phases(&world), vec![ContactPhase::End]);
+    assert!(phases(&world).is_empty());
+}
+
+#[test]
+fn test_contacts_per_hitbox() {
+    fn phases(world: &PhysicsWorld) -> Vec<(usize, ContactPhase)> {
+        let mut res: Vec<_> = world.update(0.1).iter().map(|&(phase, ref c)| (c.data1.tag, phase)).collect();
+        res.sort();
+        res
+    }
+
+    // Two hitboxes of the same entity touching the other one
+    let touching = Rc::new(RefCell::new(vec![1, 2]));
+    let world = PhysicsWorld::new(1.0, Box::new(scripted::Scripted { touching: touching.clone() }));
+    assert_eq!(phases(&world), vec![(1, ContactPhase::Begin), (2, ContactPhase::Begin)]);
+    *touching.borrow_mut() = vec![1];
+    assert_eq!(phases(&world), vec![(1, ContactPhase::Persist), (2, ContactPhase::End)]);
+    *touching.borrow_mut() = vec![1, 3];
+    assert_eq!(phases(&world), vec![(1, ContactPhase::Persist), (3, ContactPhase::Begin)]);
 }
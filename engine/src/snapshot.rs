@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use Engine;
+use TIMESTEP;
+use entity::Entity;
+use resource::Resources;
+
+/// State of every entity which supports snapshots, kept as spawners in dispatch order,
+/// along with the resources kept in snapshots
+pub struct Snapshot<E: Entity> {
+    pub spawners: Vec<Box<Fn(&Engine<E>) -> E>>,
+    pub resources: Resources,
+}
+
+/// Snapshots taken at known times of a timeline (usually a level), used to seek without
+/// simulating everything from the start
+pub struct Checkpoints<E: Entity> {
+    timeline: String,
+    saved: Vec<(f32, Rc<Snapshot<E>>)>,
+}
+
+impl<E: Entity> Checkpoints<E> {
+    pub fn new() -> Checkpoints<E> {
+        Checkpoints {
+            timeline: String::new(),
+            saved: Vec::new(),
+        }
+    }
+
+    pub fn timeline(&self) -> &str {
+        &self.timeline
+    }
+
+    /// Switches to another timeline, dropping the checkpoints of the previous one
+    pub fn select(&mut self, timeline: &str) {
+        if self.timeline != timeline {
+            self.timeline = String::from(timeline);
+            self.saved.clear();
+        }
+    }
+
+    pub fn insert(&mut self, time: f32, snapshot: Snapshot<E>) {
+        let snapshot = Rc::new(snapshot);
+        match self.saved.iter().position(|&(t, _)| t > time - TIMESTEP / 2.0) {
+            Some(i) if self.saved[i].0 < time + TIMESTEP / 2.0 => self.saved[i] = (time, snapshot),
+            Some(i) => self.saved.insert(i, (time, snapshot)),
+            None => self.saved.push((time, snapshot)),
+        }
+    }
+
+    /// Most recent checkpoint taken at or before `time`
+    pub fn latest(&self, time: f32) -> Option<(f32, Rc<Snapshot<E>>)> {
+        self.saved.iter()
+            .rev()
+            .find(|&&(t, _)| t < time + TIMESTEP / 2.0)
+            .map(|&(t, ref s)| (t, s.clone()))
+    }
+
+    pub fn clear(&mut self) {
+        self.saved.clear();
+    }
+}
+
+#[test]
+fn test_checkpoint_lookup() {
+    use event::Dummy;
+
+    let empty = || Snapshot::<Dummy> { spawners: vec![], resources: Resources::new() };
+    let mut checkpoints = Checkpoints::new();
+    checkpoints.select("level.toml");
+    checkpoints.insert(10.0, empty());
+    checkpoints.insert(5.0, empty());
+    checkpoints.insert(10.0, empty());
+    assert!(checkpoints.latest(4.0).is_none());
+    assert_eq!(checkpoints.latest(7.5).unwrap().0, 5.0);
+    assert_eq!(checkpoints.latest(10.0).unwrap().0, 10.0);
+    assert_eq!(checkpoints.saved.len(), 2);
+    checkpoints.select("other.toml");
+    assert!(checkpoints.latest(20.0).is_none());
+}
@@ -0,0 +1,82 @@
+/// Simulation clock controls, shared by every entity through the event handler
+#[derive(Clone, Debug)]
+pub struct TimeController {
+    paused: bool,
+    pending_steps: u32,
+    scale: f32,
+}
+
+impl Default for TimeController {
+    fn default() -> TimeController {
+        TimeController::new()
+    }
+}
+
+impl TimeController {
+    pub fn new() -> TimeController {
+        TimeController {
+            paused: false,
+            pending_steps: 0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Lets a single simulation step through while paused
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Multiplier applied to the length of every simulation step
+    pub fn set_scale(&mut self, scale: f32) {
+        if scale > 0.0 {
+            self.scale = scale;
+        } else {
+            println!("Ignoring invalid time scale {:?}", scale);
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Length of the next simulation step, None if the simulation shouldn't advance
+    pub fn next_step(&mut self, dt: f32) -> Option<f32> {
+        if !self.paused {
+            Some(dt * self.scale)
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            Some(dt * self.scale)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_pause_and_step() {
+    let mut time = TimeController::new();
+    time.set_scale(0.5);
+    assert_eq!(time.next_step(1.0), Some(0.5));
+    time.pause();
+    assert_eq!(time.next_step(1.0), None);
+    time.step();
+    assert_eq!(time.next_step(1.0), Some(0.5));
+    assert_eq!(time.next_step(1.0), None);
+    time.resume();
+    assert_eq!(time.next_step(1.0), Some(0.5));
+}
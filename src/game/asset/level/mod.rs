@@ -16,8 +16,10 @@ use engine::util::{HashMap};
 use engine::util;
 use game::asset;
 use game::object::level::LevelEvent;
-use game::object::level::bullet::{Bullet, Behavior};
-use game::object::level::enemy::Enemy;
+use game::object::level::bullet::{Bullet, Behavior, Laser, LaserKind};
+use game::object::level::enemy::{Drops, Enemy};
+use game::object::level::player::{Bomb, BombKind, Player};
+use game::object::level::boss::{Boss, Phase, Spell};
 
 use game::object::level::spawn::{SpawnBuilder, SpawnType, Spawn};
 use game::object::level::path::{PathBuilder, PathType, RotationDirection};
@@ -31,10 +33,13 @@ pub type Events = HashMap<String, Vec<LevelEvent>>;
 pub type Sprites = HashMap<String, usize>;
 pub type Enemies = HashMap<String, Enemy>;
 pub type Bullets = HashMap<String, Bullet>;
+pub type Bosses = HashMap<String, Boss>;
 
 static ZERO: i64 = 0;
+/// Score for destroying an enemy which doesn't set its own
+static KILL_POINTS: i64 = 100;
 
-pub fn load_level_file(graphics: Rc<RefCell<Graphics>>, path: &str) -> (Result<(Sprites, Enemies, Bullets, Events), String>) {
+pub fn load_level_file(graphics: Rc<RefCell<Graphics>>, path: &str) -> (Result<(Sprites, Enemies, Bullets, Bosses, Events, Player), String>) {
     let mut f = File::open(path).unwrap();
     let mut s = String::new();
     match f.read_to_string(&mut s) {
@@ -50,20 +55,24 @@ pub fn load_level_file(graphics: Rc<RefCell<Graphics>>, path: &str) -> (Result<(
     }
 }
 
-pub fn parse_level(graphics: Rc<RefCell<Graphics>>, level: toml::Table) -> Result<(Sprites, Enemies, Bullets, Events), String> {
+pub fn parse_level(graphics: Rc<RefCell<Graphics>>, level: toml::Table) -> Result<(Sprites, Enemies, Bullets, Bosses, Events, Player), String> {
     let mut sprites = util::hashmap();
     let mut enemies = util::hashmap();
     let mut bullets = util::hashmap();
+    let mut bosses = util::hashmap();
     let mut events = util::hashmap();
+    let mut player = Player::default();
 
     let v = Vec::new();
     for import in tget!(level, "import", Value::Array, "level config", &v) {
         match load_level_file(graphics.clone(), &(String::from("assets/levels/") + import.as_str().unwrap())[..]) {
-            Ok((s, e, b, ev)) => {
+            Ok((s, e, b, bo, ev, p)) => {
                 sprites.extend(s);
                 enemies.extend(e);
                 bullets.extend(b);
+                bosses.extend(bo);
                 events.extend(ev);
+                player = p;
             }
             Err(e) => println!("Failed to load file {}, error: {}", import.as_str().unwrap(), e)
         }
@@ -79,9 +88,107 @@ pub fn parse_level(graphics: Rc<RefCell<Graphics>>, level: toml::Table) -> Resul
     let bullet_tab = tget!(level, "bullets", Value::Table, "level config", &empty_tab);
     bullets.extend(try!(load_bullets(bullet_tab.clone(), &sprites)));
 
+    let boss_tab = tget!(level, "bosses", Value::Table, "level config", &empty_tab);
+    bosses.extend(try!(load_bosses(boss_tab.clone(), &sprites, &bullets)));
+
     let event_tab = tget!(level, "level", Value::Table, "level config", &empty_tab);
-    events.extend(try!(load_events(event_tab.clone(), &enemies, &bullets)));
-    Ok((sprites, enemies, bullets, events))
+    events.extend(try!(load_events(event_tab.clone(), &enemies, &bullets, &bosses)));
+
+    match level.get("player") {
+        Some(&Value::Table(ref pt)) => player = try!(load_player(pt, &sprites)),
+        Some(_) => return Err(String::from("Player settings must be a table")),
+        None => { }
+    }
+    Ok((sprites, enemies, bullets, bosses, events, player))
+}
+
+fn load_player(player: &toml::Table, sprites: &HashMap<String, usize>) -> Result<Player, String> {
+    let default = Player::default();
+    let lives = *tget!(player, "lives", Value::Integer, "player settings", &(default.lives as i64));
+    if lives < 1 {
+        return Err(format!("The player needs at least one life, got {:?}", lives));
+    }
+    let shot_damage = *tget!(player, "shot_damage", Value::Integer, "player settings", &(default.shot_damage as i64));
+    if shot_damage < 0 {
+        return Err(format!("The player's shot damage can't be negative, got {:?}", shot_damage));
+    }
+    let bombs = *tget!(player, "bombs", Value::Integer, "player settings", &(default.bombs as i64));
+    if bombs < 0 {
+        return Err(format!("The player can't start with a negative amount of bombs, got {:?}", bombs));
+    }
+    let mut characters = util::hashmap();
+    match player.get("characters") {
+        Some(&Value::Table(ref ct)) => {
+            for (name, character) in ct {
+                let bomb = match character.as_table().and_then(|c| c.get("bomb")) {
+                    Some(&Value::Table(ref bt)) => try!(load_bomb(bt, name, sprites)),
+                    Some(_) => return Err(format!("The bomb of character {:?} must be a table", name)),
+                    None => return Err(format!("Character {:?} has no bomb", name)),
+                };
+                characters.insert(name.clone(), bomb);
+            }
+        }
+        Some(_) => return Err(String::from("The player's characters must be a table")),
+        None => { }
+    }
+    // Without any characters every player gets the default bomb
+    let bomb = match player.get("character") {
+        Some(&Value::String(ref name)) => {
+            match characters.get(name) {
+                Some(bomb) => *bomb,
+                None => return Err(format!("Character {:?} isn't defined in [player.characters]", name)),
+            }
+        }
+        Some(_) => return Err(String::from("The player's character must be a character name")),
+        None if characters.is_empty() => default.bomb,
+        None => return Err(String::from("A character must be chosen when characters are defined")),
+    };
+    Ok(Player {
+        lives: lives as usize,
+        shot_damage: shot_damage as usize,
+        bombs: bombs as usize,
+        bomb: bomb,
+    })
+}
+
+fn load_bomb(bomb: &toml::Table, character: &String, sprites: &HashMap<String, usize>) -> Result<Bomb, String> {
+    let parse_pos = format!("bomb of character {}", character);
+    let default = Bomb::default();
+    let kind = match &tget!(bomb, "type", Value::String, parse_pos)[..] {
+        "radius" => {
+            let radius = tget!(bomb, "radius", num, parse_pos);
+            if radius <= 0.0 {
+                return Err(format!("The bomb radius of character {:?} must be positive", character));
+            }
+            BombKind::Radius(radius)
+        }
+        "screen" => BombKind::Screen,
+        t => return Err(format!("Bomb type must be 'radius' or 'screen', {:?} is invalid", t)),
+    };
+    let sprite = match bomb.get("sprite") {
+        Some(&Value::String(ref name)) => {
+            match sprites.get(name) {
+                Some(s) => Some(*s),
+                None => return Err(format!("Invalid sprite {:?} used for the bomb of character {:?}", name, character)),
+            }
+        }
+        Some(_) => return Err(String::from("The bomb sprite must be a sprite name")),
+        None => None,
+    };
+    let damage = *tget!(bomb, "damage", Value::Integer, parse_pos, &(default.damage as i64));
+    if damage < 0 {
+        return Err(format!("The bomb damage of character {:?} can't be negative", character));
+    }
+    let duration = tget!(bomb, "duration", num, parse_pos, default.duration);
+    if duration <= 0.0 {
+        return Err(format!("The bomb of character {:?} must last a positive duration", character));
+    }
+    Ok(Bomb {
+        kind: kind,
+        damage: damage as usize,
+        duration: duration,
+        sprite: sprite,
+    })
 }
 
 fn load_sprites(graphics: Rc<RefCell<Graphics>>, sprite_tab: toml::Table) -> Result<HashMap<String, usize>, String>{
@@ -129,8 +236,11 @@ fn load_enemies(enemy_tab: toml::Table, sprites: &HashMap<String, usize>) -> Res
     let mut enemies = util::hashmap();
     for (enemy_name, enemy) in enemy_tab {
         let enemy = enemy.as_table().unwrap();
-        let health = *tget!(enemy, "health", Value::Integer, enemy_name) as usize;
-        let damage = *tget!(enemy, "damage", Value::Integer, enemy_name) as usize;
+        let health = *tget!(enemy, "health", Value::Integer, enemy_name);
+        if health < 1 {
+            return Err(format!("Enemy {:?} needs at least 1 health, got {:?}", enemy_name, health));
+        }
+        try!(reject_damage(enemy, &format!("Enemy {:?}", enemy_name)));
         let sprite_name = tget!(enemy, "sprite", Value::String, enemy_name);
         let sprite = match sprites.get(sprite_name) {
             Some(s) => *s,
@@ -140,22 +250,52 @@ fn load_enemies(enemy_tab: toml::Table, sprites: &HashMap<String, usize>) -> Res
                                    enemy_name))
             }
         };
+        let points = *tget!(enemy, "points", Value::Integer, enemy_name, &KILL_POINTS) as u64;
+        let drops = match enemy.get("drops") {
+            Some(&Value::Table(ref dt)) => {
+                let parse_pos = format!("Enemy {:?} drops", enemy_name);
+                let point = *tget!(dt, "point", Value::Integer, parse_pos, &ZERO);
+                let power = *tget!(dt, "power", Value::Integer, parse_pos, &ZERO);
+                let life = *tget!(dt, "life", Value::Integer, parse_pos, &ZERO);
+                let bomb = *tget!(dt, "bomb", Value::Integer, parse_pos, &ZERO);
+                if point < 0 || power < 0 || life < 0 || bomb < 0 {
+                    return Err(format!("Enemy {:?} can't drop a negative amount of items", enemy_name));
+                }
+                Drops {
+                    point: point as usize,
+                    power: power as usize,
+                    life: life as usize,
+                    bomb: bomb as usize,
+                }
+            }
+            Some(_) => return Err(format!("Drops of enemy {:?} must be a table", enemy_name)),
+            None => Drops::default(),
+        };
         enemies.insert(enemy_name,
                        Enemy {
                            sprite: sprite,
-                           health: health,
-                           damage: damage,
+                           health: health as usize,
+                           points: points,
+                           drops: drops,
                        });
     }
     Ok(enemies)
 }
 
+/// Any hit costs the player one life, so enemies, bullets and bosses can't set a damage
+fn reject_damage(table: &toml::Table, parse_pos: &str) -> Result<(), String> {
+    if table.contains_key("damage") {
+        return Err(format!("{} sets a damage, but any hit costs the player one life", parse_pos));
+    }
+    Ok(())
+}
+
 fn load_bullets(bullet_tab: toml::Table, sprites: &HashMap<String, usize>) -> Result<Bullets, String> {
     let mut bullets = util::hashmap();
     for (bullet_name, bullet) in bullet_tab {
         let parse_pos = format!("Bullet {:?}", bullet_name);
         let bullet = bullet.as_table().unwrap();
-        let damage = *tget!(bullet, "damage", Value::Integer, bullet_name) as usize;
+        try!(reject_damage(bullet, &parse_pos));
         let sprite_name = tget!(bullet, "sprite", Value::String, bullet_name);
         let sprite = match sprites.get(sprite_name) {
             Some(s) => *s,
@@ -165,7 +305,8 @@ fn load_bullets(bullet_tab: toml::Table, sprites: &HashMap<String, usize>) -> Re
                                    bullet_name))
             }
         };
-        let behavior = match &tget!(bullet, "behavior", Value::String, bullet_name)[..] {
+        let straight = String::from("straight");
+        let behavior = match &tget!(bullet, "behavior", Value::String, bullet_name, &straight)[..] {
             "straight" => Behavior::Straight,
             "deaccel" => {
                 let time = tget!(bullet, "deaccel_time", num, parse_pos);
@@ -178,17 +319,112 @@ fn load_bullets(bullet_tab: toml::Table, sprites: &HashMap<String, usize>) -> Re
                                    bullet_name))
             }
         };
+        let orient = *tget!(bullet, "orient", Value::Boolean, bullet_name, &false);
+        let laser = match bullet.get("laser") {
+            Some(&Value::Table(ref lt)) => {
+                let parse_pos = format!("Bullet {:?} laser", bullet_name);
+                Some(try!(load_laser(lt, parse_pos)))
+            }
+            Some(_) => return Err(format!("Laser of bullet {:?} must be a table", bullet_name)),
+            None => None,
+        };
         bullets.insert(bullet_name,
                        Bullet {
                            sprite: sprite,
                            behavior: behavior,
-                           damage: damage,
+                           orient: orient,
+                           laser: laser,
                        });
     }
     Ok(bullets)
 }
 
-fn load_spawn(spawn: toml::Table, enemies: &Enemies, bullets: &Bullets, event_name: String) -> Result<Vec<Spawn>, String> {
+fn load_bosses(boss_tab: toml::Table, sprites: &HashMap<String, usize>, bullets: &Bullets) -> Result<Bosses, String> {
+    let mut bosses = util::hashmap();
+    for (boss_name, boss) in boss_tab {
+        let parse_pos = format!("Boss {:?}", boss_name);
+        let boss = match boss.as_table() {
+            Some(b) => b,
+            None => return Err(format!("{} must be a table", parse_pos)),
+        };
+        let sprite_name = tget!(boss, "sprite", Value::String, parse_pos);
+        let sprite = match sprites.get(sprite_name) {
+            Some(s) => *s,
+            None => return Err(format!("Invalid sprite {:?} used for boss {:?}", sprite_name, boss_name)),
+        };
+        try!(reject_damage(boss, &parse_pos));
+        let mut phases = Vec::new();
+        for (i, phase) in tget!(boss, "phases", Value::Array, parse_pos).iter().enumerate() {
+            let parse_pos = format!("Boss {:?} phase {:?}", boss_name, i + 1);
+            match phase.as_table() {
+                Some(pt) => phases.push(try!(load_phase(pt, bullets, parse_pos))),
+                None => return Err(format!("{} must be a table", parse_pos)),
+            }
+        }
+        if phases.is_empty() {
+            return Err(format!("Boss {:?} needs at least one phase", boss_name));
+        }
+        bosses.insert(boss_name,
+                      Boss {
+                          sprite: sprite,
+                          phases: phases,
+                      });
+    }
+    Ok(bosses)
+}
+
+fn load_phase(phase: &toml::Table, bullets: &Bullets, parse_pos: String) -> Result<Phase, String> {
+    let health = *tget!(phase, "health", Value::Integer, parse_pos);
+    if health < 1 {
+        return Err(format!("{} needs at least 1 health, got {:?}", parse_pos, health));
+    }
+    let time_limit = tget!(phase, "time_limit", num, parse_pos);
+    let loop_time = match phase.get("loop") {
+        Some(v) => Some(tint!(v, parse_pos)),
+        None => None,
+    };
+    let spell = match phase.get("spell") {
+        Some(&Value::Table(ref st)) => {
+            Some(Spell {
+                name: tget!(st, "name", Value::String, parse_pos).clone(),
+                bonus: *tget!(st, "bonus", Value::Integer, parse_pos, &ZERO) as u64,
+            })
+        }
+        Some(_) => return Err(format!("Spell card of {} must be a table", parse_pos)),
+        None => None,
+    };
+    let actions = try!(load_actions(phase.get("action"), bullets, format!("{} action", parse_pos)));
+    Ok(Phase {
+        health: health as usize,
+        time_limit: time_limit,
+        actions: actions,
+        loop_time: loop_time,
+        spell: spell,
+    })
+}
+
+fn load_laser(laser: &toml::Table, parse_pos: String) -> Result<Laser, String> {
+    let kind = match &tget!(laser, "type", Value::String, parse_pos)[..] {
+        "straight" => LaserKind::Straight(tget!(laser, "sweep", num, parse_pos, 0.0)),
+        "curvy" => LaserKind::Curvy(tget!(laser, "turn", num, parse_pos, 0.0)),
+        t => return Err(format!("Laser type must be 'straight' or 'curvy', {:?} is invalid", t)),
+    };
+    let length = tget!(laser, "length", num, parse_pos);
+    let width = tget!(laser, "width", num, parse_pos);
+    if length <= 0.0 || width <= 0.0 {
+        return Err(format!("Length and width of {} must be positive", parse_pos));
+    }
+    Ok(Laser {
+        kind: kind,
+        length: length,
+        width: width,
+        warmup: tget!(laser, "warmup", num, parse_pos, 0.0),
+        duration: tget!(laser, "duration", num, parse_pos),
+        fade: tget!(laser, "fade", num, parse_pos, 0.0),
+    })
+}
+
+fn load_spawn(spawn: toml::Table, enemies: &Enemies, bullets: &Bullets, bosses: &Bosses, event_name: String) -> Result<Vec<Spawn>, String> {
     let parse_pos = format!("{:?} spawn", event_name);
     let default_loc = vec![toml::Value::Integer(0), toml::Value::Integer(0)];
     let point = tget!(spawn, "location", Value::Array, parse_pos, &default_loc);
@@ -198,6 +434,13 @@ fn load_spawn(spawn: toml::Table, enemies: &Enemies, bullets: &Bullets, event_na
         "player" => {
             Ok(vec![Spawn::player(location)])
         }
+        "boss" => {
+            let boss_name = tget!(spawn, "boss_id", Value::String, parse_pos);
+            match bosses.get(boss_name) {
+                Some(b) => Ok(vec![Spawn::boss(b.clone(), location)]),
+                None => Err(format!("Invalid boss {:?} used in {:?}", boss_name, parse_pos)),
+            }
+        }
         "enemy" => {
             let enemy_name = tget!(spawn, "enemy_id", Value::String, parse_pos);
             let enemy = match enemies.get(enemy_name) {
@@ -224,25 +467,8 @@ fn load_spawn(spawn: toml::Table, enemies: &Enemies, bullets: &Bullets, event_na
             for path in path_ar {
                 let path_tab = path.as_table().unwrap();
 
-                let actions = {
-                    match path_tab.get("action") {
-                        Some(&Value::Array(ref a)) => {
-                            let mut actions= Vec::new();
-                            let mut actions_arr = a.clone();
-                            while let Some(Value::Table(action_tab)) = actions_arr.pop() {
-                                let parse_pos = format!("{:?} spawn action", event_name);
-                                actions.push(try!(load_action(action_tab.clone(), bullets, parse_pos.clone())));
-                            }
-                            actions
-                        }
-                        Some(&Value::Table(ref action_tab)) => {
-                            let parse_pos = format!("{:?} spawn action", event_name);
-                            let action = try!(load_action(action_tab.clone(), bullets, parse_pos.clone()));
-                            vec![action]
-                        }
-                        _ => vec![]
-                    }
-                };
+                let parse_pos = format!("{:?} spawn action", event_name);
+                let actions = try!(load_actions(path_tab.get("action"), bullets, parse_pos));
 
                 let parse_pos = format!("{:?} spawn path {:?}", event_name, pn);
                 let path = try!(load_path(path_tab.clone(), parse_pos)).actions(actions);
@@ -268,7 +494,26 @@ fn load_spawn(spawn: toml::Table, enemies: &Enemies, bullets: &Bullets, event_na
             spawns.push(spawn.build(&Vector2::new(0.0, 0.0), &Vector2::new(0.0, 0.0)));
             Ok(spawns)
         }
-        s => Err(format!("Spawn must be 'player' or 'enemy', {:?} is invalid", s)),
+        s => Err(format!("Spawn must be 'player', 'enemy' or 'boss', {:?} is invalid", s)),
+    }
+}
+
+/// Loads either a single action table or an array of them
+fn load_actions(actions: Option<&Value>, bullets: &Bullets, parse_pos: String) -> Result<Vec<Action>, String> {
+    match actions {
+        Some(&Value::Array(ref a)) => {
+            let mut actions= Vec::new();
+            let mut actions_arr = a.clone();
+            while let Some(Value::Table(action_tab)) = actions_arr.pop() {
+                actions.push(try!(load_action(action_tab.clone(), bullets, parse_pos.clone())));
+            }
+            Ok(actions)
+        }
+        Some(&Value::Table(ref action_tab)) => {
+            let action = try!(load_action(action_tab.clone(), bullets, parse_pos.clone()));
+            Ok(vec![action])
+        }
+        _ => Ok(vec![])
     }
 }
 
@@ -423,7 +668,7 @@ fn load_path(path: toml::Table, parse_pos: String) -> Result<PathBuilder, String
     }
 }
 
-fn load_events(event_tab: toml::Table, enemies: &Enemies, bullets: &Bullets) -> Result<HashMap<String, Vec<LevelEvent>>, String> {
+fn load_events(event_tab: toml::Table, enemies: &Enemies, bullets: &Bullets, bosses: &Bosses) -> Result<HashMap<String, Vec<LevelEvent>>, String> {
     let mut events: HashMap<String, Vec<LevelEvent>> = util::hashmap();
     let mut ev_counter = 0;
     let insert_or_app = |map: &mut HashMap<String, Vec<LevelEvent>>, key: String, val: LevelEvent| {
@@ -442,7 +687,7 @@ fn load_events(event_tab: toml::Table, enemies: &Enemies, bullets: &Bullets) ->
         let delay = tget!(ev_timing, "delay", num, event_name);
 
         let spawn_tab = tget!(event, "spawn", Value::Table, parse_pos);
-        let spawns = try!(load_spawn(spawn_tab.clone(), enemies, bullets, event_name.clone()));
+        let spawns = try!(load_spawn(spawn_tab.clone(), enemies, bullets, bosses, event_name.clone()));
         insert_or_app(&mut events, ev_after.clone(),
             LevelEvent {
                 name: event_name.clone(),
@@ -7,11 +7,11 @@ use engine::graphics::{Graphics};
 
 use nalgebra::{Vector2};
 use ncollide_geometry::shape::{ShapeHandle2, Ball};
-use glium::VertexBuffer;
 
 use engine::Engine;
 use engine::graphics::SpriteVertex;
 use game::object::Object;
+use game::resource::GameSprites;
 
 const SPRITE_VERT_SHADER: &'static str = r#"
     #version 130
@@ -44,6 +44,8 @@ pub fn load_assets(engine: &mut Engine<Object>) {
     println!("Loading assets!");
     load_char(engine);
     load_bullet(engine);
+    let item = load_item(engine);
+    engine.resources.borrow_mut().insert(GameSprites { item: item });
     load_sound(engine);
     load_fonts(engine);
 }
@@ -67,40 +69,36 @@ pub fn make_sprite(graphics: Rc<RefCell<Graphics>>,
                -> usize {
     let vert_shader = SPRITE_VERT_SHADER;
     let frag_shader = SPRITE_FRAG_SHADER;
-    let vbo = make_vbo(graphics.clone(), half_extents);
     let mut gfx = graphics.borrow_mut();
-    let texture = gfx.load_texture(&texture[..]);
+    let vertices = make_vertices(half_extents / gfx.scaler);
     let id = gfx.sprite_amount() + 1;
     gfx.new_sprite(id,
                    vert_shader,
                    frag_shader,
-                   vbo,
+                   &vertices,
                    Some(texture),
                    amount,
                    Some(shape));
     id
 }
 
-fn make_vbo(graphics: Rc<RefCell<Graphics>>, half_extents: Vector2<f32>) -> VertexBuffer<SpriteVertex> {
-    let half_extents = half_extents / graphics.borrow().scaler;
-    let vertices = &[SpriteVertex {
-                         position: [-1.0 * half_extents.x, -1.0 * half_extents.y],
-                         tex_coords: [0.0, 0.0],
-                     },
-                     SpriteVertex {
-                         position: [-1.0 * half_extents.x, half_extents.y],
-                         tex_coords: [0.0, 1.0],
-                     },
-                     SpriteVertex {
-                         position: [half_extents.x, half_extents.y],
-                         tex_coords: [1.0, 1.0],
-                     },
-                     SpriteVertex {
-                         position: [half_extents.x, -1.0 * half_extents.y],
-                         tex_coords: [1.0, 0.0],
-                     }];
-    let gfx = graphics.borrow();
-    gfx.make_sprite_vbo(vertices)
+fn make_vertices(half_extents: Vector2<f32>) -> [SpriteVertex; 4] {
+    [SpriteVertex {
+         position: [-1.0 * half_extents.x, -1.0 * half_extents.y],
+         tex_coords: [0.0, 0.0],
+     },
+     SpriteVertex {
+         position: [-1.0 * half_extents.x, half_extents.y],
+         tex_coords: [0.0, 1.0],
+     },
+     SpriteVertex {
+         position: [half_extents.x, half_extents.y],
+         tex_coords: [1.0, 1.0],
+     },
+     SpriteVertex {
+         position: [half_extents.x, -1.0 * half_extents.y],
+         tex_coords: [1.0, 0.0],
+     }]
 }
 
 fn load_char(engine: &mut Engine<Object>) {
@@ -120,3 +118,12 @@ fn load_bullet(engine: &mut Engine<Object>) {
                 300,
                 shape);
 }
+
+fn load_item(engine: &mut Engine<Object>) -> usize {
+    let shape = ShapeHandle2::new(Ball::new(6.0));
+    make_sprite(engine.graphics.clone(),
+                "assets/sprites/bullet.png",
+                Vector2::new(4.0, 4.0),
+                500,
+                shape)
+}
@@ -1,5 +1,20 @@
+use nalgebra::Vector2;
+
 use game::asset::level::Events;
+use game::object::item::ItemKind;
+use game::object::level::player::Player;
 
 pub enum Event {
-    LevelStart(Events, i32),
+    /// Level events, player settings, start time and the level file they were loaded from
+    LevelStart(Events, Player, i32, String),
+    /// The player lost their last life
+    GameOver,
+    /// Damage dealt to the receiving entity
+    Damage(usize),
+    /// An enemy ran out of health at the given position, sent to the level
+    EnemyKilled(Vector2<f32>),
+    /// Removes enemy bullets within the radius of the position, or all of them without a radius
+    ClearBullets(Vector2<f32>, Option<f32>),
+    /// An item of the kind and point value was picked up, sent to the player
+    ItemCollected(ItemKind, u64),
 }
@@ -2,4 +2,5 @@ pub mod object;
 pub mod asset;
 pub mod event;
 pub mod physics;
+pub mod resource;
 
@@ -0,0 +1,149 @@
+use nalgebra::Vector2;
+use std::rc::Rc;
+
+use engine::Engine;
+use engine::entity::component::*;
+use engine::event::Event;
+use engine::scene::{Handle, PhysicsWorld};
+use game::event::Event as CEvent;
+use game::object::Object;
+use game::object::level::player::{Bomb as BombInfo, BombKind};
+use game::physics::ENEMY;
+
+/// Time between two hits on the enemies in the area
+const DAMAGE_INTERVAL: f32 = 0.1;
+
+/// A bomb used by the player, lives for the bomb's duration
+pub struct Bomb {
+    info: BombInfo,
+    pos: Vector2<f32>,
+    time: f32,
+    sprite: Option<GraphicsComp>,
+    sprite_size: (f32, f32),
+    scaler: f32,
+    physics: Rc<PhysicsWorld>,
+    ev: EventComp<Object>,
+    world: WorldComp<Object>,
+}
+
+impl Bomb {
+    pub fn new(engine: &Engine<Object>, info: BombInfo, pos: Vector2<f32>) -> Object {
+        Object::Bomb(Bomb::build(engine, info, pos))
+    }
+
+    fn build(engine: &Engine<Object>, info: BombInfo, pos: Vector2<f32>) -> Bomb {
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
+        let e = EventComp::new(w.id, engine.events.clone());
+        // Screen bombs are drawn over the whole playfield
+        let pos = match info.kind {
+            BombKind::Radius(_) => pos,
+            BombKind::Screen => Vector2::new(0.0, 0.0),
+        };
+        let sprite = info.sprite.map(|s| GraphicsComp::new(engine.graphics.clone(), s));
+        let sprite_size = match info.sprite {
+            Some(s) => engine.graphics.borrow().get_sprite_size(&s).unwrap_or((1.0, 1.0)),
+            None => (1.0, 1.0),
+        };
+        let mut bomb = Bomb {
+            info: info,
+            pos: pos,
+            time: 0.0,
+            sprite: sprite,
+            sprite_size: sprite_size,
+            scaler: engine.scene.physics.scaler,
+            physics: engine.scene.physics.clone(),
+            ev: e,
+            world: w,
+        };
+        bomb.layout();
+        bomb
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let (info, pos, time) = (self.info, self.pos, self.time);
+        let timers = self.ev.timers();
+        Some(Box::new(move |engine| {
+            let mut bomb = Bomb::build(engine, info, pos);
+            bomb.time = time;
+            bomb.ev.set_timers(timers.clone());
+            bomb.layout();
+            Object::Bomb(bomb)
+        }))
+    }
+
+    pub fn handle_event(&mut self, e: Rc<Event>) {
+        match *e {
+            Event::Spawn => {
+                self.ev.set_repeating_timer(1, DAMAGE_INTERVAL);
+                self.clear_bullets();
+                self.damage_enemies();
+            }
+            Event::Update(t) => {
+                self.ev.update(t);
+                self.time += t;
+                self.clear_bullets();
+                self.layout();
+                if self.time >= self.info.duration {
+                    self.ev.destroy_self();
+                }
+            }
+            Event::Timer(1) => {
+                self.damage_enemies();
+            }
+            Event::Render => {
+                if let Some(ref sprite) = self.sprite {
+                    sprite.render();
+                }
+            }
+            _ => {}
+        };
+    }
+
+    pub fn id(&self) -> Handle {
+        self.world.id
+    }
+
+    fn radius(&self) -> Option<f32> {
+        match self.info.kind {
+            BombKind::Radius(r) => Some(r),
+            BombKind::Screen => None,
+        }
+    }
+
+    fn clear_bullets(&self) {
+        self.ev.dispatch_custom(CEvent::ClearBullets(self.pos, self.radius()));
+    }
+
+    fn damage_enemies(&self) {
+        let hits = match self.info.kind {
+            BombKind::Radius(r) => self.physics.overlap_circle(self.pos, r, &[ENEMY]),
+            BombKind::Screen => {
+                let playfield = self.physics.playfield();
+                let center = (playfield.mins + playfield.maxs) * 0.5;
+                let half_extents = (playfield.maxs - playfield.mins) * 0.5;
+                self.physics.overlap_rect(center, half_extents, &[ENEMY])
+            }
+        };
+        let mut enemies: Vec<_> = hits.iter().map(|hit| hit.data.entity_id).collect();
+        enemies.sort();
+        enemies.dedup();
+        for id in enemies {
+            self.ev.dispatch_custom_to(id, CEvent::Damage(self.info.damage));
+        }
+    }
+
+    /// Grows the sprite over the area and shrinks it again as the bomb runs out
+    fn layout(&mut self) {
+        let extent = match self.info.kind {
+            BombKind::Radius(r) => 2.0 * r,
+            BombKind::Screen => 2.0 * self.scaler,
+        };
+        let left = 1.0 - self.time / self.info.duration;
+        let size = extent * (self.time * 4.0).min(1.0).min(left * 4.0).max(0.0);
+        let (pos, scaler, sprite_size) = (self.pos, self.scaler, self.sprite_size);
+        if let Some(ref mut sprite) = self.sprite {
+            sprite.set_pos(pos.x / scaler, pos.y / scaler);
+            sprite.set_scale(size / sprite_size.0, size / sprite_size.1);
+        }
+    }
+}
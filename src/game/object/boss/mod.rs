@@ -0,0 +1,306 @@
+use nalgebra::Vector2;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use engine::Engine;
+use engine::entity::component::*;
+use engine::event::Event;
+use engine::scene::Handle;
+use game::event::Event as CEvent;
+use game::object::Object;
+use game::object::enemy::{fire_patterns, PosFetcher};
+use game::object::level::action::ActionType;
+use game::object::level::boss::{Boss as BossInfo, Phase};
+use game::object::level::bullet::Bullet as BulletInfo;
+use game::object::level::pattern::Pattern;
+use game::physics::ENEMY;
+use game::resource::{BossState, Counts, PlayerState, Score};
+
+/// A boss fought through the phases of its level definition
+pub struct Boss {
+    info: BossInfo,
+    /// Index of the current phase
+    phase: usize,
+    health: usize,
+    /// Time spent in the current phase
+    time: f32,
+    /// Time since the bullet script of the phase last started
+    script_time: f32,
+    /// Which actions of the script already fired during this run
+    fired: Vec<bool>,
+    patterns: Vec<(BulletInfo, Pattern)>,
+    /// Deaths and bombs of the player when the phase started, any more lose the capture
+    misses: (usize, usize),
+    pg: PGComp,
+    ev: EventComp<Object>,
+    world: WorldComp<Object>,
+    res: ResourceComp,
+    pos: Cell<Vector2<f32>>,
+}
+
+impl Boss {
+    pub fn new(engine: &Engine<Object>, info: BossInfo, pos: Vector2<f32>) -> Object {
+        let mut boss = Boss::build(engine, info, pos);
+        boss.start_phase(0);
+        Object::Boss(boss)
+    }
+
+    fn build(engine: &Engine<Object>, info: BossInfo, pos: Vector2<f32>) -> Boss {
+        let mut g = GraphicsComp::new(engine.graphics.clone(), info.sprite);
+        let w = WorldCompBuilder::new(engine)
+            .with_alias(String::from("boss"))
+            .with_tag(String::from("level"))
+            .build();
+        let e = EventComp::new(w.id, engine.events.clone());
+        let scaler = engine.scene.physics.scaler;
+        let p = PhysicsComp::new(w.id,
+                                 0,
+                                 pos,
+                                 engine.graphics.borrow().get_sprite_shape(&info.sprite).unwrap(),
+                                 ENEMY,
+                                 &engine.scene);
+        g.translate(pos.x / scaler, pos.y / scaler);
+        let pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
+        Boss {
+            info: info,
+            phase: 0,
+            health: 0,
+            time: 0.0,
+            script_time: 0.0,
+            fired: Vec::new(),
+            patterns: Vec::new(),
+            misses: (0, 0),
+            pg: pg,
+            ev: e,
+            world: w,
+            res: ResourceComp::new(engine),
+            pos: Cell::new(pos),
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let info = self.info.clone();
+        let (phase, health, time, script_time) = (self.phase, self.health, self.time, self.script_time);
+        let fired = self.fired.clone();
+        let patterns = self.patterns.clone();
+        let misses = self.misses;
+        let pos = self.pg.get_vpos();
+        Some(Box::new(move |engine| {
+            let mut boss = Boss::build(engine, info.clone(), pos);
+            boss.phase = phase;
+            boss.health = health;
+            boss.time = time;
+            boss.script_time = script_time;
+            boss.fired = fired.clone();
+            boss.misses = misses;
+            boss.patterns = patterns.clone();
+            Object::Boss(boss)
+        }))
+    }
+
+    pub fn handle_event(&mut self, e: Rc<Event>) {
+        match *e {
+            Event::Spawn | Event::Restore => {
+                self.res.update(|c: &mut Counts| c.enemies += 1);
+                self.update_state();
+            }
+            Event::Despawn => {
+                self.res.update(|c: &mut Counts| c.enemies -= 1);
+                self.res.set(BossState::default());
+            }
+            Event::Update(t) => {
+                self.ev.update(t);
+                self.update(t);
+            }
+            Event::Render => {
+                self.pg.render();
+            }
+            Event::Custom(ref cev) => {
+                if let Some(&CEvent::Damage(amount)) = cev.get::<CEvent>() {
+                    self.take_damage(amount);
+                }
+            }
+            _ => {}
+        };
+    }
+
+    pub fn id(&self) -> Handle {
+        self.world.id
+    }
+
+    fn current(&self) -> &Phase {
+        &self.info.phases[self.phase]
+    }
+
+    fn start_phase(&mut self, phase: usize) {
+        self.phase = phase;
+        self.health = self.current().health;
+        self.time = 0.0;
+        self.script_time = 0.0;
+        self.fired = vec![false; self.current().actions.len()];
+        self.patterns.clear();
+        let score = self.res.get_or_default::<Score>();
+        self.misses = (score.deaths, score.bombs_used);
+        self.update_state();
+    }
+
+    fn update(&mut self, dt: f32) {
+        if self.health == 0 {
+            // Defeated, waiting to be removed
+            return;
+        }
+        self.time += dt;
+        if self.time >= self.current().time_limit {
+            self.end_phase(false);
+            return;
+        }
+        self.run_script(dt);
+        fire_patterns(&mut self.patterns, self.pg.get_vpos(), dt, &self.ev, &self.res);
+        self.pos.set(self.pg.get_vpos());
+        self.update_state();
+    }
+
+    /// Fires the actions whose delay passed, restarting the script if the phase loops it
+    fn run_script(&mut self, dt: f32) {
+        self.script_time += dt;
+        let loop_time = self.current().loop_time;
+        if let Some(loop_time) = loop_time {
+            if loop_time > 0.0 && self.script_time >= loop_time {
+                self.script_time -= loop_time;
+                for fired in self.fired.iter_mut() {
+                    *fired = false;
+                }
+            }
+        }
+        let mut due = Vec::new();
+        for (i, action) in self.current().actions.iter().enumerate() {
+            if !self.fired[i] && action.delay <= self.script_time {
+                due.push(action.action_type);
+            }
+        }
+        for i in 0..self.fired.len() {
+            if self.current().actions[i].delay <= self.script_time {
+                self.fired[i] = true;
+            }
+        }
+        for action in due {
+            self.handle_action(action);
+        }
+    }
+
+    fn handle_action(&mut self, a: ActionType) {
+        match a {
+            ActionType::Bullets(bullet, pb) => {
+                let ppos = self.res.get_or_default::<PlayerState>().pos;
+                let mut pattern = pb.build(&self.pg.get_vpos(), &ppos);
+                pattern.set_pos_fetcher(PosFetcher::new(self.pos.clone(), self.res.clone()));
+                self.patterns.push((bullet, pattern));
+            }
+            ActionType::None => {}
+        }
+    }
+
+    fn take_damage(&mut self, amount: usize) {
+        if self.health == 0 {
+            return;
+        }
+        self.health = self.health.saturating_sub(amount);
+        if self.health == 0 {
+            self.end_phase(true);
+        }
+    }
+
+    /// Awards the spell bonus if it was captured, clears the bullets and moves on
+    fn end_phase(&mut self, defeated: bool) {
+        let score = self.res.get_or_default::<Score>();
+        let clean = (score.deaths, score.bombs_used) == self.misses;
+        if defeated && clean {
+            if let Some(ref spell) = self.current().spell {
+                let bonus = spell.bonus;
+                println!("Spell card {:?} captured!", spell.name);
+                self.res.update(|s: &mut Score| {
+                    s.points += bonus;
+                    s.captures += 1;
+                });
+            }
+        }
+        let pos = self.pg.get_vpos();
+        self.ev.dispatch_custom(CEvent::ClearBullets(pos, None));
+
+        if self.phase + 1 < self.info.phases.len() {
+            let next = self.phase + 1;
+            self.start_phase(next);
+        } else {
+            self.health = 0;
+            if defeated {
+                if let Some(level) = self.world.find_aliased_entity_id(&String::from("level")) {
+                    self.ev.dispatch_custom_to(level, CEvent::EnemyKilled(pos));
+                }
+            }
+            self.ev.destroy_self();
+        }
+    }
+
+    fn update_state(&self) {
+        let phase = self.current();
+        let state = BossState {
+            active: true,
+            health: self.health,
+            max_health: phase.health,
+            time_left: (phase.time_limit - self.time).max(0.0),
+            phases_left: self.info.phases.len() - self.phase - 1,
+            spell: phase.spell.as_ref().map(|s| s.name.clone()),
+        };
+        self.res.set(state);
+    }
+}
+
+#[test]
+fn test_capture_needs_clean_phase() {
+    use ncollide_geometry::shape::{Ball, ShapeHandle2};
+    use game::asset::make_sprite;
+    use game::object::level::boss::Spell;
+    use game::physics::DanmakuPhysics;
+
+    let engine: Engine<Object> = Engine::headless(200.0, 700, Box::new(DanmakuPhysics::new(200.0)));
+    let sprite = make_sprite(engine.graphics.clone(),
+                             "assets/sprites/cirno.png",
+                             Vector2::new(10.0, 20.0),
+                             1,
+                             ShapeHandle2::new(Ball::new(10.0)));
+    let phase = Phase {
+        health: 10,
+        time_limit: 30.0,
+        actions: Vec::new(),
+        loop_time: None,
+        spell: Some(Spell {
+            name: String::from("Test Sign"),
+            bonus: 1000,
+        }),
+    };
+    let info = BossInfo {
+        sprite: sprite,
+        phases: vec![phase; 4],
+    };
+    let res = ResourceComp::new(&engine);
+    res.set(Score::default());
+    let mut boss = Boss::build(&engine, info, Vector2::new(0.0, 100.0));
+    boss.start_phase(0);
+
+    boss.take_damage(10);
+    assert_eq!(res.get_or_default::<Score>().captures, 1);
+
+    res.update(|s: &mut Score| s.deaths += 1);
+    boss.take_damage(10);
+    assert_eq!(res.get_or_default::<Score>().captures, 1);
+
+    res.update(|s: &mut Score| s.bombs_used += 1);
+    boss.take_damage(10);
+    assert_eq!(res.get_or_default::<Score>().captures, 1);
+
+    // Earlier misses don't count against later phases
+    boss.take_damage(10);
+    let score = res.get_or_default::<Score>();
+    assert_eq!(score.captures, 2);
+    assert_eq!(score.points, 2000);
+}
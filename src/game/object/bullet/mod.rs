@@ -1,103 +1,317 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::f32;
 use std::rc::Rc;
 
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::Event;
+use engine::graphics::Graphics;
 use engine::util::{ToCartesian, ToPolar};
-use nalgebra::Vector2;
+use engine::scene::{Handle, PhysicsWorld, Registry};
+use nalgebra::{Norm, Vector2};
 
+use game::event::Event as CEvent;
 use game::object::Object;
 use game::object::level::bullet::{Bullet as BulletInfo, Behavior};
+use game::physics::ENEMY_BULLET;
+use game::resource::{BulletQueue, Counts};
 
-pub static mut BULLET_COUNT: usize = 0;
+/// Runs after everything that can queue bullets, so they are added on the tick they were fired
+const MANAGER_PRIORITY: i32 = 100;
 
-pub struct Bullet {
-    pub damage: usize,
-    pg: PGComp,
+/// Every enemy bullet in the level, updated and drawn by a single entity.
+/// Bullets are kept as parallel arrays and removed by swapping with the last one
+pub struct BulletManager {
+    infos: Vec<BulletInfo>,
+    pos: Vec<Vector2<f32>>,
+    vel: Vec<Vector2<f32>>,
+    accel: Vec<Vector2<f32>>,
+    rot: Vec<f32>,
+    hitboxes: Vec<PhysicsComp>,
+    /// Hitbox tags, unique per bullet so each one is its own contact
+    tags: Vec<usize>,
+    /// Hands out tags, returned ones are only reused on the next update so the
+    /// physics world has seen the old bullet go first
+    slots: Registry,
+    /// One batch per sprite in use
+    batches: BTreeMap<usize, SpriteBatch>,
+    /// Amount of bullets last added to `Counts`
+    counted: usize,
+    scaler: f32,
+    graphics: Rc<RefCell<Graphics>>,
+    physics: Rc<PhysicsWorld>,
     ev: EventComp<Object>,
     world: WorldComp<Object>,
-    behavior: Behavior,
+    res: ResourceComp,
 }
 
-impl Bullet {
-    pub fn new(engine: &Engine<Object>, info: BulletInfo, pos: Vector2<f32>, vel: Vector2<f32>) -> Object {
-        let mut g = GraphicsComp::new(engine.graphics.clone(), info.sprite);
-        let w = WorldCompBuilder::new(engine).build();
+impl BulletManager {
+    pub fn new(engine: &Engine<Object>) -> Object {
+        Object::BulletManager(BulletManager::build(engine))
+    }
+
+    fn build(engine: &Engine<Object>) -> BulletManager {
+        let w = WorldCompBuilder::new(engine).with_alias(String::from("bullets")).with_tag(String::from("level")).build();
         let e = EventComp::new(w.id, engine.events.clone());
-        let scaler = engine.scene.physics.scaler;
-        let p = PhysicsComp::new(w.id,
-                                 0,
-                                 pos,
-                                 engine.graphics.borrow().get_sprite_shape(&info.sprite).unwrap(),
-                                 2,
-                                 &engine.scene);
-        g.translate(pos.x / scaler, pos.y / scaler);
-        let mut pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
-        pg.velocity = vel;
-        match info.behavior {
-            Behavior::Deaccelerate(_, ref accel) => {
-                let angle = vel.to_polar().y;
-                pg.acceleration = -1.0 * Vector2::new(*accel, angle).to_cartesian();
-            }
-            _ => { }
-        }
-        Object::Bullet(Bullet {
-            behavior: info.behavior,
-            damage: info.damage,
-            pg: pg,
+        BulletManager {
+            infos: Vec::new(),
+            pos: Vec::new(),
+            vel: Vec::new(),
+            accel: Vec::new(),
+            rot: Vec::new(),
+            hitboxes: Vec::new(),
+            tags: Vec::new(),
+            slots: Registry::new(),
+            batches: BTreeMap::new(),
+            counted: 0,
+            scaler: engine.scene.physics.scaler,
+            graphics: engine.graphics.clone(),
+            physics: engine.scene.physics.clone(),
             ev: e,
             world: w,
-        })
+            res: ResourceComp::new(engine),
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let bullets: Vec<_> = (0..self.len())
+            .map(|i| (self.infos[i], self.pos[i], self.vel[i], self.accel[i]))
+            .collect();
+        Some(Box::new(move |engine| {
+            let mut manager = BulletManager::build(engine);
+            for &(info, pos, vel, accel) in bullets.iter() {
+                manager.add(info, pos, vel);
+                let last = manager.len() - 1;
+                manager.accel[last] = accel;
+            }
+            Object::BulletManager(manager)
+        }))
     }
 
     pub fn handle_event(&mut self, e: Rc<Event>) {
         match *e {
-            Event::Spawn => {
-                unsafe { BULLET_COUNT += 1 };
-                self.ev.set_repeating_timer(1, 1.0);
+            Event::Spawn | Event::Restore => {
+                self.ev.set_priority(MANAGER_PRIORITY);
+                self.ev.subscribe_custom::<CEvent>();
+                self.update_count();
             }
-            Event::Timer(1) => {
-                if !self.pg.in_screen() {
-                    unsafe { BULLET_COUNT -= 1 };
-                    self.ev.destroy_self();
-                }
+            Event::Despawn => {
+                let counted = self.counted;
+                self.res.update(|c: &mut Counts| c.bullets -= counted);
             }
             Event::Update(t) => {
                 self.ev.update(t);
-                self.pg.update(t);
-                match self.behavior {
-                    Behavior::Deaccelerate(ref mut time, _) => {
-                        *time -= t;
-                        if *time <= 0.0 {
-                            self.pg.acceleration = Vector2::new(0.0, 0.0);
-                        }
-                    }
-                    _ => { }
-                }
-            }
-            Event::Collision(id, ref data) => {
-                if let Some(s) = self.world.find_aliased_entity_alias(&id) {
-                    match &s[..] {
-                        "player" => {
-                            // self.ev.destroy_self();
-                        }
-                        _ => { }
-                    }
-                }
-                //pub struct CollisionData {
-                //    pub contact: Contact<Point2<f32>>,
-                //    pub this_object: Rc<PhysicsData>,
-                //    pub other_object: Rc<PhysicsData>,
-                //}
+                self.update(t);
             }
             Event::Render => {
-                self.pg.render();
+                self.render();
+            }
+            Event::Custom(ref cev) => {
+                if let Some(&CEvent::ClearBullets(center, radius)) = cev.get::<CEvent>() {
+                    self.clear(center, radius);
+                }
             }
             _ => {}
         };
     }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
+
+    pub fn len(&self) -> usize {
+        self.infos.len()
+    }
+
+    fn add(&mut self, mut info: BulletInfo, pos: Vector2<f32>, vel: Vector2<f32>) {
+        let shape = match self.graphics.borrow().get_sprite_shape(&info.sprite) {
+            Some(shape) => shape,
+            None => {
+                println!("Bullet sprite {} has no shape", info.sprite);
+                return;
+            }
+        };
+        let accel = match info.behavior {
+            Behavior::Deaccelerate(_, accel) => {
+                let angle = vel.to_polar().y;
+                -1.0 * Vector2::new(accel, angle).to_cartesian()
+            }
+            _ => Vector2::new(0.0, 0.0),
+        };
+        // Lasers are entities of their own
+        info.laser = None;
+        let (graphics, sprite) = (self.graphics.clone(), info.sprite);
+        let acquired = self.batches.entry(sprite).or_insert_with(|| SpriteBatch::new(graphics, sprite)).acquire();
+        // A bullet which can't be drawn mustn't be able to hit either
+        if !acquired {
+            println!("Bullet sprite {} has no instances left, bullet dropped", sprite);
+            return;
+        }
+        let tag = self.slots.get_id();
+        let hitbox = PhysicsComp::in_world(self.world.id, tag, pos, shape, ENEMY_BULLET, self.physics.clone());
+        self.infos.push(info);
+        self.pos.push(pos);
+        self.vel.push(vel);
+        self.accel.push(accel);
+        self.rot.push(0.0);
+        self.hitboxes.push(hitbox);
+        self.tags.push(tag);
+        let last = self.len() - 1;
+        self.orient(last);
+        let rot = self.rot[last];
+        self.hitboxes[last].set_rot(rot);
+    }
+
+    fn remove(&mut self, i: usize) {
+        if let Some(batch) = self.batches.get_mut(&self.infos[i].sprite) {
+            batch.release();
+        }
+        self.infos.swap_remove(i);
+        self.pos.swap_remove(i);
+        self.vel.swap_remove(i);
+        self.accel.swap_remove(i);
+        self.rot.swap_remove(i);
+        self.hitboxes.swap_remove(i);
+        self.slots.return_id(self.tags[i]);
+        self.tags.swap_remove(i);
+    }
+
+    /// Removes the bullets within `radius` of `center`, or every bullet without a radius
+    fn clear(&mut self, center: Vector2<f32>, radius: Option<f32>) {
+        let mut i = 0;
+        while i < self.len() {
+            let inside = match radius {
+                Some(r) => (self.pos[i] - center).norm() <= r,
+                None => true,
+            };
+            if inside {
+                self.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        self.update_count();
+    }
+
+    fn orient(&mut self, i: usize) {
+        let v = self.vel[i];
+        if self.infos[i].orient && (v.x != 0.0 || v.y != 0.0) {
+            self.rot[i] = v.y.atan2(v.x) - f32::consts::FRAC_PI_2;
+        }
+    }
+
+    /// Moves every bullet, then hands all the new hitbox positions to the physics world at once
+    fn update(&mut self, dt: f32) {
+        self.slots.reclaim();
+        let playfield = self.physics.playfield();
+        let mut moves = Vec::with_capacity(self.len());
+        let mut i = 0;
+        while i < self.len() {
+            self.vel[i] += self.accel[i] * dt;
+            self.pos[i] += self.vel[i] * dt;
+            if let Behavior::Deaccelerate(ref mut time, _) = self.infos[i].behavior {
+                *time -= dt;
+                if *time <= 0.0 {
+                    self.accel[i] = Vector2::new(0.0, 0.0);
+                }
+            }
+            if !playfield.contains(&self.pos[i]) {
+                self.remove(i);
+                continue;
+            }
+            self.orient(i);
+            self.hitboxes[i].pos = self.pos[i];
+            self.hitboxes[i].rot = self.rot[i];
+            moves.push((self.hitboxes[i].id, self.pos[i], self.rot[i]));
+            i += 1;
+        }
+        self.physics.set_positions(&moves);
+
+        let queued = self.res.update(|q: &mut BulletQueue| q.take());
+        for (info, pos, vel) in queued {
+            self.add(info, pos, vel);
+        }
+        self.update_count();
+    }
+
+    /// Lasers are counted as bullets too, so only the difference is applied
+    fn update_count(&mut self) {
+        let (len, counted) = (self.len(), self.counted);
+        self.res.update(|c: &mut Counts| c.bullets = c.bullets + len - counted);
+        self.counted = len;
+    }
+
+    fn render(&mut self) {
+        for batch in self.batches.values_mut() {
+            batch.clear();
+        }
+        for i in 0..self.len() {
+            if let Some(batch) = self.batches.get_mut(&self.infos[i].sprite) {
+                batch.push(self.pos[i].x / self.scaler, self.pos[i].y / self.scaler, self.rot[i]);
+            }
+        }
+        for batch in self.batches.values_mut() {
+            batch.render();
+        }
+    }
+}
+
+#[test]
+fn test_many_bullets() {
+    use ncollide_geometry::shape::{Ball, ShapeHandle2};
+    use engine::TIMESTEP;
+    use engine::event::CustomEvent;
+    use game::asset::make_sprite;
+    use game::physics::DanmakuPhysics;
+
+    const BULLETS: usize = 5000;
+    let engine: Engine<Object> = Engine::headless(200.0, 700, Box::new(DanmakuPhysics::new(200.0)));
+    let sprite = make_sprite(engine.graphics.clone(),
+                             "assets/sprites/bullet.png",
+                             Vector2::new(4.0, 4.0),
+                             BULLETS,
+                             ShapeHandle2::new(Ball::new(3.0)));
+    let info = BulletInfo {
+        sprite: sprite,
+        behavior: Behavior::Straight,
+        orient: true,
+        laser: None,
+    };
+    let res = ResourceComp::new(&engine);
+    let mut manager = BulletManager::build(&engine);
+    for i in 0..BULLETS {
+        let pos = Vector2::new((i % 100) as f32 * 3.0 - 150.0, (i / 100) as f32 * 3.0 - 75.0);
+        // Every other bullet leaves the playfield on the first update
+        let vel = if i % 2 == 0 { Vector2::new(0.0, 1.0) } else { Vector2::new(0.0, 60000.0) };
+        manager.add(info, pos, vel);
+    }
+    // The sprite is out of instances
+    manager.add(info, Vector2::new(0.0, 0.0), Vector2::new(0.0, 1.0));
+    assert_eq!(manager.len(), BULLETS);
+    assert_eq!(manager.hitboxes.len(), BULLETS);
+
+    manager.update(TIMESTEP);
+    assert_eq!(manager.len(), BULLETS / 2);
+    assert_eq!(res.get_or_default::<Counts>().bullets, BULLETS / 2);
+    assert!(manager.pos.iter().all(|p| p.y < 100.0));
+    // Culled bullets gave their instances back
+    manager.add(info, Vector2::new(0.0, 0.0), Vector2::new(0.0, 1.0));
+    assert_eq!(manager.len(), BULLETS / 2 + 1);
+
+    let mut tags = manager.tags.clone();
+    tags.sort();
+    tags.dedup();
+    assert_eq!(tags.len(), manager.len());
+
+    let center = Vector2::new(0.0, 0.0);
+    let clear = |radius| Rc::new(Event::Custom(CustomEvent::new(CEvent::ClearBullets(center, radius))));
+    manager.handle_event(clear(Some(50.0)));
+    assert!(manager.len() > 0 && manager.len() < BULLETS / 2);
+    assert!(manager.pos.iter().all(|p| (*p - center).norm() > 50.0));
+    assert_eq!(res.get_or_default::<Counts>().bullets, manager.len());
+
+    manager.handle_event(clear(None));
+    assert_eq!(manager.len(), 0);
+    assert_eq!(res.get_or_default::<Counts>().bullets, 0);
 }
@@ -3,26 +3,44 @@ use std::rc::Rc;
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::Event;
+use engine::scene::Handle;
 use game::event::Event as CEvent;
 
 use game::object::Object;
 use game::object::mouse::Mouse;
 use game::object::menu::MainMenu;
 use game::object::level::Level;
+use game::asset::level;
 
 /// Top level game controller
 pub struct Controller {
     ev: EventComp<Object>,
     world: WorldComp<Object>,
+    // Level to start immediately instead of showing the main menu
+    initial_level: Option<CEvent>,
 }
 
 impl Controller {
     pub fn new(engine: &Engine<Object>) -> Object {
+        Controller::build(engine, None)
+    }
+
+    /// Creates a controller which skips the menu and starts the level at `path` right away
+    pub fn with_level(engine: &Engine<Object>, path: &str, start_time: i32) -> Result<Object, String> {
+        let (events, player) = match level::load_level_file(engine.graphics.clone(), path) {
+            Ok((_, _, _, _, events, player)) => (events, player),
+            Err(e) => return Err(e),
+        };
+        Ok(Controller::build(engine, Some(CEvent::LevelStart(events, player, start_time, String::from(path)))))
+    }
+
+    fn build(engine: &Engine<Object>, initial_level: Option<CEvent>) -> Object {
         let w = WorldCompBuilder::new(engine).with_alias(String::from("controller")).build();
         let e = EventComp::new(w.id, engine.events.clone());
         Object::Controller(Controller {
             ev: e,
             world: w,
+            initial_level: initial_level,
         })
     }
 
@@ -31,10 +49,15 @@ impl Controller {
             Event::Spawn => {
                 println!("Spawned controller!");
                 self.ev.create_entity(Box::new(|engine| Mouse::new(engine)));
-                self.ev.create_entity(Box::new(|engine| MainMenu::new(engine)));
+                match self.initial_level.take() {
+                    Some(level) => self.ev.dispatch_custom_to(self.world.id, level),
+                    None => self.ev.create_entity(Box::new(|engine| MainMenu::new(engine))),
+                }
             }
             Event::Custom(ref cev) => {
-                self.handle_cevent(cev.downcast_ref::<CEvent>().unwrap());
+                if let Some(e) = cev.get::<CEvent>() {
+                    self.handle_cevent(e);
+                }
             }
             _ => {}
         };
@@ -42,27 +65,36 @@ impl Controller {
 
     fn handle_cevent(&mut self, e: &CEvent) {
         match *e {
-            CEvent::LevelStart(ref level, ref time) => {
+            CEvent::LevelStart(ref level, player, ref time, ref path) => {
                 let l = level.clone();
-                let menu = self.world.find_aliased_entity_id(&String::from("main_menu")).unwrap();
-                self.ev.destroy_other(menu);
+                if let Some(menu) = self.world.find_aliased_entity_id(&String::from("main_menu")) {
+                    self.ev.destroy_other(menu);
+                }
                 let ftime = *time as f32;
-                if ftime <  0.0001 {
-                    self.ev.create_entity(Box::new(move |engine| {
-                        Level::new(engine, l.clone())
-                    }));
-                
-                } else {
-                    self.ev.create_entity(Box::new(move |engine| {
-                        Level::new_at_nearest_time(engine, l.clone(), ftime)
-                    }));
+                self.ev.create_entity(Box::new(move |engine| {
+                    Level::new(engine, l.clone(), player)
+                }));
+                self.ev.seek(path.clone(), ftime);
+                self.ev.start_session(path.clone(), ftime);
+            }
+            CEvent::GameOver => {
+                println!("Game over!");
+                if let Some(ids) = self.world.get_tagged(&String::from("level")) {
+                    for id in ids {
+                        self.ev.destroy_other(id);
+                    }
                 }
-                self.ev.fast_forward(ftime);
+                self.ev.create_entity(Box::new(|engine| MainMenu::new(engine)));
             }
+            _ => { }
         }
     }
 
-    pub fn id(&self) -> usize {
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        None
+    }
+
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
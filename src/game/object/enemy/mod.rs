@@ -1,4 +1,4 @@
-use nalgebra::Vector2;
+use nalgebra::{Norm, Vector2};
 use std::rc::Rc;
 use std::cell::Cell;
 use std::mem;
@@ -6,47 +6,86 @@ use std::mem;
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::Event;
+use engine::scene::Handle;
 use game::event::Event as CEvent;
 use game::object::Object;
 use game::object::level::path::{Path, PathBuilder};
 use game::object::level::pattern::Pattern;
-use game::object::level::enemy::Enemy as EnemyInfo;
+use game::object::item::{Item, ItemKind};
+use game::object::level::enemy::{Drops, Enemy as EnemyInfo};
 use game::object::level::action::ActionType;
 use game::object::level::bullet::Bullet as BulletInfo;
-use game::object::bullet::Bullet;
-use game::object::player::PLAYER_POSITION;
+use game::object::laser::Laser;
+use game::resource::{BulletQueue, Counts, PlayerState, Score};
 
-pub static mut ENEMY_COUNT: usize = 0;
+/// Horizontal speed between neighbouring drops of a destroyed enemy
+const DROP_SPREAD: f32 = 15.0;
 
 #[derive(Clone)]
 pub struct PosFetcher {
     pos: Cell<Vector2<f32>>,
-    world: WorldComp<Object>,
+    res: ResourceComp,
 }
 
 use std::fmt;
 
 impl fmt::Debug for PosFetcher {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "PosFetcher Object, derived from world comp with id: {}", self.world.id)
+        write!(f, "PosFetcher Object at {:?}", self.pos.get())
     }
 }
 
 impl PosFetcher {
-    pub fn new(pos: Cell<Vector2<f32>>, world: WorldComp<Object>) -> PosFetcher {
-        PosFetcher { pos: pos, world: world }
+    pub fn new(pos: Cell<Vector2<f32>>, res: ResourceComp) -> PosFetcher {
+        PosFetcher { pos: pos, res: res }
     }
 
     pub fn fetch(&self) -> (Vector2<f32>, Vector2<f32>) {
-        // muh performance :')
-        let ppos = unsafe { PLAYER_POSITION.clone() };
+        let ppos = self.res.get_or_default::<PlayerState>().pos;
         (self.pos.get(), ppos)
     }
 }
 
+/// Advances bullet patterns fired from `origin`, dropping the ones which are done.
+/// Bullets go to the bullet manager, lasers become entities of their own
+pub fn fire_patterns(patterns: &mut Vec<(BulletInfo, Pattern)>,
+                     origin: Vector2<f32>,
+                     dt: f32,
+                     ev: &EventComp<Object>,
+                     res: &ResourceComp) {
+    let mut done_pats = Vec::new();
+    for (i, &mut (ref bullet, ref mut pat)) in patterns.iter_mut().enumerate() {
+        let spawns = pat.next(dt);
+        if spawns.len() == 0 && pat.finished() {
+            done_pats.push(i);
+            continue;
+        }
+        for (pos, vel, angle) in spawns {
+            let b = bullet.clone();
+            let pos = pos + origin;
+            match b.laser {
+                Some(laser) => {
+                    let speed = vel.norm();
+                    ev.create_entity(Box::new(move |engine| {
+                        Laser::new(engine, laser, b.sprite, pos, angle, speed)
+                    }));
+                }
+                None => {
+                    res.update(|q: &mut BulletQueue| q.push(b, pos, vel));
+                }
+            }
+        }
+    }
+    for i in done_pats.into_iter().rev() {
+        patterns.remove(i);
+    }
+}
+
 pub struct Enemy {
     health: usize,
-    pub damage: usize,
+    sprite: usize,
+    points: u64,
+    drops: Drops,
     paths: Vec<PathBuilder>,
     cpath: Path,
     actions: Vec<ActionType>,
@@ -54,6 +93,7 @@ pub struct Enemy {
     pg: PGComp,
     ev: EventComp<Object>,
     world: WorldComp<Object>,
+    res: ResourceComp,
     pos: Cell<Vector2<f32>>,
 }
 
@@ -63,28 +103,9 @@ impl Enemy {
                pos: Vector2<f32>,
                mut paths: Vec<PathBuilder>)
                -> Object {
-        let mut g = GraphicsComp::new(engine.graphics.clone(), info.sprite);
-        let w = WorldCompBuilder::new(engine).build();
-        let mut e = EventComp::new(w.id, engine.events.clone());
-        let scaler = engine.scene.physics.scaler;
-        let p = PhysicsComp::new(w.id,
-                                 0,
-                                 Vector2::new(pos.x, pos.y),
-                                 engine.graphics.borrow().get_sprite_shape(&info.sprite).unwrap(),
-                                 4,
-                                 &engine.scene);
-        g.translate(pos.x / scaler, pos.y / scaler);
-        let pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
-
-        let ppos = {
-            let pid = w.find_aliased_entity_id(&String::from("player")).unwrap();
-            let ea = w.get_entity(&pid);
-            let p = match *ea.access().unwrap() {
-                Object::Player(ref p) => p.get_pos(),
-                _ => panic!("Non player object aliased to player!"),
-            };
-            p
-        };
+        let (pg, mut e, w) = Enemy::components(engine, info.sprite, pos);
+        let res = ResourceComp::new(engine);
+        let ppos = res.get_or_default::<PlayerState>().pos;
         // Makes it easy to pop from the back
         paths.reverse();
         let mut path = paths.pop().unwrap().build(&pos, &ppos);
@@ -99,20 +120,75 @@ impl Enemy {
             health: info.health,
             paths: paths,
             cpath: path,
-            damage: info.damage,
+            sprite: info.sprite,
+            points: info.points,
+            drops: info.drops,
             actions: actions,
             patterns: Vec::new(),
             pg: pg,
             ev: e,
             world: w,
+            res: res,
             pos: Cell::new(cpos),
         })
     }
 
+    fn components(engine: &Engine<Object>,
+                  sprite: usize,
+                  pos: Vector2<f32>)
+                  -> (PGComp, EventComp<Object>, WorldComp<Object>) {
+        let mut g = GraphicsComp::new(engine.graphics.clone(), sprite);
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
+        let e = EventComp::new(w.id, engine.events.clone());
+        let scaler = engine.scene.physics.scaler;
+        let p = PhysicsComp::new(w.id,
+                                 0,
+                                 Vector2::new(pos.x, pos.y),
+                                 engine.graphics.borrow().get_sprite_shape(&sprite).unwrap(),
+                                 4,
+                                 &engine.scene);
+        g.translate(pos.x / scaler, pos.y / scaler);
+        let pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
+        (pg, e, w)
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let (health, sprite) = (self.health, self.sprite);
+        let (points, drops) = (self.points, self.drops);
+        let paths = self.paths.clone();
+        let cpath = self.cpath.clone();
+        let actions = self.actions.clone();
+        let patterns = self.patterns.clone();
+        let pos = self.pg.get_vpos();
+        let timers = self.ev.timers();
+        Some(Box::new(move |engine| {
+            let (pg, mut e, w) = Enemy::components(engine, sprite, pos);
+            e.set_timers(timers.clone());
+            Object::Enemy(Enemy {
+                health: health,
+                paths: paths.clone(),
+                cpath: cpath.clone(),
+                sprite: sprite,
+                points: points,
+                drops: drops,
+                actions: actions.clone(),
+                patterns: patterns.clone(),
+                pg: pg,
+                ev: e,
+                world: w,
+                res: ResourceComp::new(engine),
+                pos: Cell::new(pos),
+            })
+        }))
+    }
+
     pub fn handle_event(&mut self, e: Rc<Event>) {
         match *e {
-            Event::Spawn => {
-                unsafe { ENEMY_COUNT += 1};
+            Event::Spawn | Event::Restore => {
+                self.res.update(|c: &mut Counts| c.enemies += 1);
+            }
+            Event::Despawn => {
+                self.res.update(|c: &mut Counts| c.enemies -= 1);
             }
             Event::Update(t) => {
                 self.ev.update(t);
@@ -132,27 +208,11 @@ impl Enemy {
                                 }
                             }
                         } else {
-                            unsafe { ENEMY_COUNT -= 1};
                             self.ev.destroy_self();
                         }
                     }
                 };
-                let mut done_pats = Vec::new();
-                for (i, &mut (ref bullet, ref mut pat)) in self.patterns.iter_mut().enumerate() {
-                    let spawns = pat.next(t);
-                    if spawns.len() == 0 && pat.finished() {
-                        done_pats.push(i);
-                        continue;
-                    }
-                    for (pos, vel) in spawns {
-                        let b = bullet.clone();
-                        let pos = pos + self.pg.get_vpos();
-                        self.ev.create_entity(Box::new(move |engine| Bullet::new(engine, b, pos, vel)));
-                    }
-                }
-                for i in done_pats {
-                    self.patterns.remove(i);
-                }
+                fire_patterns(&mut self.patterns, self.pg.get_vpos(), t, &self.ev, &self.res);
                 self.pos.set(self.pg.get_vpos());
             }
             Event::CTimer(1, i) => {
@@ -160,20 +220,13 @@ impl Enemy {
                 let action = mem::replace(&mut self.actions[i], ActionType::None);
                 self.handle_action(action);
             }
-            Event::Collision(id, ref _data) => {
-                if let Some(s) = self.world.find_aliased_entity_alias(&id) {
-                    if &s[..] == "player" {
-                        // What do we do when we hit the player?
-                        println!("Player coliision!");
-                        // self.ev.destroy_self();
-                    }
-                }
-            }
             Event::Render => {
                 self.pg.render();
             }
             Event::Custom(ref cev) => {
-                self.handle_cevent(cev.downcast_ref::<CEvent>().unwrap());
+                if let Some(e) = cev.get::<CEvent>() {
+                    self.handle_cevent(e);
+                }
             }
             _ => {}
         };
@@ -184,7 +237,7 @@ impl Enemy {
             ActionType::Bullets(bullet, pb) => {
                 let ppos = self.get_player_pos();
                 let mut pattern = pb.build(&self.pg.get_vpos(), &ppos);
-                pattern.set_pos_fetcher(PosFetcher::new(self.pos.clone(), self.world.clone()));
+                pattern.set_pos_fetcher(PosFetcher::new(self.pos.clone(), self.res.clone()));
                 self.patterns.push((bullet.clone(), pattern.clone()));
             }
             ActionType::None => {}
@@ -193,22 +246,49 @@ impl Enemy {
 
     fn handle_cevent(&mut self, e: &CEvent) {
         match *e {
+            CEvent::Damage(amount) => self.take_damage(amount),
             _ => { }
         }
     }
 
+    /// Dies once health reaches zero, scoring its points and letting the level know where
+    fn take_damage(&mut self, amount: usize) {
+        if self.health == 0 {
+            // Already dead, waiting to be removed
+            return;
+        }
+        self.health = self.health.saturating_sub(amount);
+        if self.health == 0 {
+            let pos = self.pg.get_vpos();
+            let points = self.points;
+            self.res.update(|s: &mut Score| s.points += points);
+            self.drop_items(pos);
+            if let Some(level) = self.world.find_aliased_entity_id(&String::from("level")) {
+                self.ev.dispatch_custom_to(level, CEvent::EnemyKilled(pos));
+            }
+            self.ev.destroy_self();
+        }
+    }
+
+    /// Throws out the enemy's drops, fanned out sideways around `pos`
+    fn drop_items(&self, pos: Vector2<f32>) {
+        let mut kinds = Vec::new();
+        kinds.extend((0..self.drops.point).map(|_| ItemKind::Point));
+        kinds.extend((0..self.drops.power).map(|_| ItemKind::Power));
+        kinds.extend((0..self.drops.life).map(|_| ItemKind::LifePiece));
+        kinds.extend((0..self.drops.bomb).map(|_| ItemKind::BombPiece));
+        let middle = (kinds.len() as f32 - 1.0) / 2.0;
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let spread = (i as f32 - middle) * DROP_SPREAD;
+            self.ev.create_entity(Box::new(move |engine| Item::dropped(engine, kind, pos, spread)));
+        }
+    }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 
     fn get_player_pos(&self) -> Vector2<f32> {
-        let pid = self.world.find_aliased_entity_id(&String::from("player")).unwrap();
-        let ea = self.world.get_entity(&pid);
-        let p = match *ea.access().unwrap() {
-            Object::Player(ref p) => p.get_pos(),
-            _ => panic!("Non player object aliased to player!"),
-        };
-        p
+        self.res.get_or_default::<PlayerState>().pos
     }
 }
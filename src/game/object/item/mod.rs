@@ -0,0 +1,160 @@
+use nalgebra::{Norm, Vector2};
+use std::rc::Rc;
+
+use engine::Engine;
+use engine::entity::component::*;
+use engine::event::Event;
+use engine::scene::{CullPolicy, Handle};
+use game::event::Event as CEvent;
+use game::object::Object;
+use game::physics::{ITEM, PLAYER};
+use game::resource::{GameSprites, PlayerState};
+
+/// Speed items are thrown up with when dropped
+const POP_SPEED: f32 = 60.0;
+const GRAVITY: f32 = 120.0;
+/// Items never fall faster than this
+const MAX_FALL_SPEED: f32 = 80.0;
+/// Speed of items flying to the player once auto collected
+const HOMING_SPEED: f32 = 400.0;
+/// Height of the point of collection line as a share of the distance from the center to the top,
+/// above it every item flies to the player and is worth its full value
+const POC_HEIGHT: f32 = 0.5;
+/// Share of the full value a point item is worth when collected at the very bottom
+const MIN_VALUE_SHARE: f32 = 0.1;
+/// Full value of a point item
+const POINT_VALUE: u64 = 10000;
+/// Points given for a power item
+const POWER_VALUE: u64 = 10;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ItemKind {
+    Point,
+    Power,
+    LifePiece,
+    BombPiece,
+}
+
+/// A drop falling down the playfield until the player collects it
+pub struct Item {
+    kind: ItemKind,
+    /// Flying to the player, which makes it worth its full value
+    homing: bool,
+    pg: PGComp,
+    ev: EventComp<Object>,
+    world: WorldComp<Object>,
+    res: ResourceComp,
+}
+
+impl Item {
+    pub fn new(engine: &Engine<Object>, kind: ItemKind, pos: Vector2<f32>, vel: Vector2<f32>) -> Object {
+        Object::Item(Item::build(engine, kind, pos, vel))
+    }
+
+    /// Item thrown up from `pos`, drifting sideways by `spread`
+    pub fn dropped(engine: &Engine<Object>, kind: ItemKind, pos: Vector2<f32>, spread: f32) -> Object {
+        Item::new(engine, kind, pos, Vector2::new(spread, POP_SPEED))
+    }
+
+    fn build(engine: &Engine<Object>, kind: ItemKind, pos: Vector2<f32>, vel: Vector2<f32>) -> Item {
+        let res = ResourceComp::new(engine);
+        let sprite = res.get::<GameSprites>().unwrap().item;
+        let mut g = GraphicsComp::new(engine.graphics.clone(), sprite);
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
+        let e = EventComp::new(w.id, engine.events.clone());
+        let scaler = engine.scene.physics.scaler;
+        let p = PhysicsComp::new(w.id,
+                                 0,
+                                 pos,
+                                 engine.graphics.borrow().get_sprite_shape(&sprite).unwrap(),
+                                 ITEM,
+                                 &engine.scene);
+        g.translate(pos.x / scaler, pos.y / scaler);
+        // Pieces are drawn bigger so they stand out
+        match kind {
+            ItemKind::Point | ItemKind::Power => g.set_scale(1.0, 1.0),
+            ItemKind::LifePiece | ItemKind::BombPiece => g.set_scale(1.5, 1.5),
+        }
+        let mut pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
+        pg.velocity = vel;
+        pg.acceleration = Vector2::new(0.0, -GRAVITY);
+        pg.set_cull(CullPolicy::Despawn);
+        Item {
+            kind: kind,
+            homing: false,
+            pg: pg,
+            ev: e,
+            world: w,
+            res: res,
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let (kind, homing) = (self.kind, self.homing);
+        let (pos, vel) = (self.pg.get_vpos(), self.pg.velocity);
+        Some(Box::new(move |engine| {
+            let mut item = Item::build(engine, kind, pos, vel);
+            item.homing = homing;
+            Object::Item(item)
+        }))
+    }
+
+    pub fn handle_event(&mut self, e: Rc<Event>) {
+        match *e {
+            Event::Update(t) => {
+                self.update(t);
+            }
+            Event::Render => {
+                self.pg.render();
+            }
+            Event::CollisionBegin(id, ref data) if data.other_object.group == PLAYER => {
+                let value = self.value();
+                self.ev.dispatch_custom_to(id, CEvent::ItemCollected(self.kind, value));
+                self.ev.destroy_self();
+            }
+            _ => {}
+        };
+    }
+
+    pub fn id(&self) -> Handle {
+        self.world.id
+    }
+
+    fn update(&mut self, dt: f32) {
+        let ppos = self.res.get_or_default::<PlayerState>().pos;
+        if ppos.y >= self.poc_line() {
+            self.homing = true;
+        }
+        if self.homing {
+            let to_player = ppos - self.pg.get_vpos();
+            if to_player.norm() > 0.001 {
+                self.pg.velocity = to_player.normalize() * HOMING_SPEED;
+            }
+            self.pg.acceleration = Vector2::new(0.0, 0.0);
+        } else if self.pg.velocity.y < -MAX_FALL_SPEED {
+            self.pg.velocity.y = -MAX_FALL_SPEED;
+        }
+        self.pg.update(dt);
+    }
+
+    fn poc_line(&self) -> f32 {
+        self.pg.scaler * POC_HEIGHT
+    }
+
+    /// Point items are worth more the higher up they are collected, power items are fixed
+    fn value(&self) -> u64 {
+        match self.kind {
+            ItemKind::Point => {
+                if self.homing {
+                    return POINT_VALUE;
+                }
+                let bottom = -self.pg.scaler;
+                let height = (self.pg.get_vpos().y - bottom) / (self.poc_line() - bottom);
+                let share = MIN_VALUE_SHARE + (1.0 - MIN_VALUE_SHARE) * height.max(0.0).min(1.0);
+                (POINT_VALUE as f32 * share) as u64
+            }
+            ItemKind::Power => POWER_VALUE,
+            ItemKind::LifePiece | ItemKind::BombPiece => 0,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use nalgebra::{Isometry2, Norm, Vector1, Vector2};
+use ncollide_geometry::shape::{Capsule, Compound, Cuboid, ShapeHandle2};
+use std::f32;
+use std::rc::Rc;
+
+use engine::Engine;
+use engine::entity::component::*;
+use engine::event::Event;
+use engine::scene::{Handle, PhysicsWorld};
+use game::event::Event as CEvent;
+use game::object::Object;
+use game::object::level::bullet::{Laser as LaserInfo, LaserKind};
+use game::physics::ENEMY_BULLET;
+use game::resource::Counts;
+
+/// Share of the full width a laser is drawn with while it telegraphs
+const TELEGRAPH_WIDTH: f32 = 0.15;
+/// Amount of segments the trail of a curvy laser is split into
+const TRAIL_SEGMENTS: usize = 16;
+
+pub struct Laser {
+    sprite: usize,
+    sprite_size: (f32, f32),
+    scaler: f32,
+    info: LaserInfo,
+    /// Origin of a straight laser, head of a curvy one
+    pos: Vector2<f32>,
+    /// Direction the laser points or its head travels in, in radians
+    angle: f32,
+    speed: f32,
+    time: f32,
+    /// Past head positions of a curvy laser, oldest first
+    trail: Vec<Vector2<f32>>,
+    sprites: Vec<GraphicsComp>,
+    /// Sprites laid out over a segment, the rest are hidden
+    shown: usize,
+    hitbox: Option<PhysicsComp>,
+    physics: Rc<PhysicsWorld>,
+    ev: EventComp<Object>,
+    world: WorldComp<Object>,
+    res: ResourceComp,
+}
+
+impl Laser {
+    pub fn new(engine: &Engine<Object>,
+               info: LaserInfo,
+               sprite: usize,
+               pos: Vector2<f32>,
+               angle: f32,
+               speed: f32)
+               -> Object {
+        Object::Laser(Laser::build(engine, info, sprite, pos, angle.to_radians(), speed))
+    }
+
+    fn build(engine: &Engine<Object>,
+             info: LaserInfo,
+             sprite: usize,
+             pos: Vector2<f32>,
+             angle: f32,
+             speed: f32)
+             -> Laser {
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
+        let e = EventComp::new(w.id, engine.events.clone());
+        let amount = match info.kind {
+            LaserKind::Straight(_) => 1,
+            LaserKind::Curvy(_) => TRAIL_SEGMENTS + 1,
+        };
+        let sprites = (0..amount).map(|_| GraphicsComp::new(engine.graphics.clone(), sprite)).collect();
+        let sprite_size = engine.graphics.borrow().get_sprite_size(&sprite).unwrap_or((1.0, 1.0));
+        let mut laser = Laser {
+            sprite: sprite,
+            sprite_size: sprite_size,
+            scaler: engine.scene.physics.scaler,
+            info: info,
+            pos: pos,
+            angle: angle,
+            speed: speed,
+            time: 0.0,
+            trail: vec![pos],
+            sprites: sprites,
+            shown: 0,
+            hitbox: None,
+            physics: engine.scene.physics.clone(),
+            ev: e,
+            world: w,
+            res: ResourceComp::new(engine),
+        };
+        laser.layout();
+        laser
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let (info, sprite) = (self.info, self.sprite);
+        let (pos, angle, speed, time) = (self.pos, self.angle, self.speed, self.time);
+        let trail = self.trail.clone();
+        let timers = self.ev.timers();
+        Some(Box::new(move |engine| {
+            let mut laser = Laser::build(engine, info, sprite, pos, angle, speed);
+            laser.time = time;
+            laser.trail = trail.clone();
+            laser.ev.set_timers(timers.clone());
+            laser.update_hitbox();
+            laser.layout();
+            Object::Laser(laser)
+        }))
+    }
+
+    pub fn handle_event(&mut self, e: Rc<Event>) {
+        match *e {
+            Event::Spawn | Event::Restore => {
+                self.ev.subscribe_custom::<CEvent>();
+                self.res.update(|c: &mut Counts| c.bullets += 1);
+            }
+            Event::Despawn => {
+                self.res.update(|c: &mut Counts| c.bullets -= 1);
+            }
+            Event::Update(t) => {
+                self.ev.update(t);
+                self.update(t);
+            }
+            Event::Render => {
+                for (i, sprite) in self.sprites.iter().enumerate() {
+                    if i < self.shown {
+                        sprite.render();
+                    } else {
+                        sprite.hide();
+                    }
+                }
+            }
+            Event::Custom(ref cev) => {
+                if let Some(&CEvent::ClearBullets(center, radius)) = cev.get::<CEvent>() {
+                    let hit = match radius {
+                        Some(r) => self.points().iter().any(|p| (*p - center).norm() <= r),
+                        None => true,
+                    };
+                    if hit {
+                        self.ev.destroy_self();
+                    }
+                }
+            }
+            _ => {}
+        };
+    }
+
+    pub fn id(&self) -> Handle {
+        self.world.id
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        match self.info.kind {
+            LaserKind::Straight(sweep) => {
+                if self.is_lethal() {
+                    self.angle += sweep.to_radians() * dt;
+                }
+            }
+            LaserKind::Curvy(turn) => {
+                self.angle += turn.to_radians() * dt;
+                self.pos += Vector2::new(self.angle.cos(), self.angle.sin()) * self.speed * dt;
+                self.extend_trail();
+            }
+        }
+        self.update_hitbox();
+        self.layout();
+        if self.time >= self.info.warmup + self.info.duration + self.info.fade {
+            self.ev.destroy_self();
+        }
+    }
+
+    fn is_lethal(&self) -> bool {
+        self.time >= self.info.warmup && self.time < self.info.warmup + self.info.duration
+    }
+
+    fn width(&self) -> f32 {
+        let active_end = self.info.warmup + self.info.duration;
+        if self.time < self.info.warmup {
+            self.info.width * TELEGRAPH_WIDTH
+        } else if self.time < active_end {
+            self.info.width
+        } else if self.info.fade > 0.0 {
+            self.info.width * (1.0 - (self.time - active_end) / self.info.fade).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Points along the laser, from its tail to its origin or head
+    fn points(&self) -> Vec<Vector2<f32>> {
+        match self.info.kind {
+            LaserKind::Straight(_) => {
+                let dir = Vector2::new(self.angle.cos(), self.angle.sin());
+                vec![self.pos + dir * self.info.length, self.pos]
+            }
+            LaserKind::Curvy(_) => {
+                let mut points = self.trail.clone();
+                points.push(self.pos);
+                points
+            }
+        }
+    }
+
+    /// Records the head position once it moved far enough and cuts the tail to the laser's length
+    fn extend_trail(&mut self) {
+        let spacing = self.info.length / TRAIL_SEGMENTS as f32;
+        let moved = match self.trail.last() {
+            Some(last) => (self.pos - *last).norm(),
+            None => spacing,
+        };
+        if moved >= spacing {
+            self.trail.push(self.pos);
+        }
+
+        let points = self.points();
+        let total = points.windows(2).fold(0.0, |acc, w| acc + (w[1] - w[0]).norm());
+        let mut excess = total - self.info.length;
+        while excess > 0.0 && !self.trail.is_empty() {
+            let next = if self.trail.len() > 1 { self.trail[1] } else { self.pos };
+            let d = (next - self.trail[0]).norm();
+            if d <= excess {
+                self.trail.remove(0);
+                excess -= d;
+            } else {
+                self.trail[0] = self.trail[0] + (next - self.trail[0]) * (excess / d);
+                break;
+            }
+        }
+    }
+
+    /// Laser body relative to the hitbox position, as wide as the laser at full width.
+    /// Curvy lasers are a capsule per segment so the joints stay covered
+    fn shape(&self) -> Option<ShapeHandle2<f32>> {
+        let half_width = self.info.width / 2.0;
+        match self.info.kind {
+            LaserKind::Straight(_) => {
+                let half_length = self.info.length / 2.0;
+                let center = Isometry2::new(Vector2::new(0.0, half_length), Vector1::new(0.0));
+                let body = ShapeHandle2::new(Cuboid::new(Vector2::new(half_width, half_length)));
+                Some(ShapeHandle2::new(Compound::new(vec![(center, body)])))
+            }
+            LaserKind::Curvy(_) => {
+                let segments: Vec<_> = self.points()
+                    .windows(2)
+                    .filter(|w| (w[1] - w[0]).norm() > 0.001)
+                    .map(|w| {
+                        let d = w[1] - w[0];
+                        let mid = (w[0] + w[1]) * 0.5 - self.pos;
+                        // Capsules run along their y axis
+                        let rot = d.y.atan2(d.x) - f32::consts::FRAC_PI_2;
+                        let capsule = Capsule::new(d.norm() / 2.0, half_width);
+                        (Isometry2::new(mid, Vector1::new(rot)), ShapeHandle2::new(capsule))
+                    })
+                    .collect();
+                if segments.is_empty() {
+                    None
+                } else {
+                    Some(ShapeHandle2::new(Compound::new(segments)))
+                }
+            }
+        }
+    }
+
+    /// Adds the hitbox once the laser becomes lethal and removes it when it starts fading
+    fn update_hitbox(&mut self) {
+        if !self.is_lethal() {
+            self.hitbox = None;
+            return;
+        }
+        match self.info.kind {
+            LaserKind::Straight(_) => {
+                if self.hitbox.is_none() {
+                    if let Some(shape) = self.shape() {
+                        self.hitbox = Some(PhysicsComp::in_world(self.world.id, 0, self.pos, shape, ENEMY_BULLET, self.physics.clone()));
+                    }
+                }
+                let rot = self.angle - f32::consts::FRAC_PI_2;
+                if let Some(ref mut hitbox) = self.hitbox {
+                    hitbox.set_rot(rot);
+                }
+            }
+            LaserKind::Curvy(_) => {
+                let shape = match self.shape() {
+                    Some(shape) => shape,
+                    None => return,
+                };
+                match self.hitbox {
+                    Some(ref mut hitbox) => {
+                        hitbox.set_pos(self.pos);
+                        hitbox.set_shape(shape);
+                        return;
+                    }
+                    None => { }
+                }
+                self.hitbox = Some(PhysicsComp::in_world(self.world.id, 0, self.pos, shape, ENEMY_BULLET, self.physics.clone()));
+            }
+        }
+    }
+
+    /// Stretches one sprite over every segment of the laser, leftover sprites are hidden
+    fn layout(&mut self) {
+        let points = self.points();
+        let width = self.width() / self.sprite_size.0;
+        let (scaler, height) = (self.scaler, self.sprite_size.1);
+        self.shown = points.len().saturating_sub(1).min(self.sprites.len());
+        for (i, sprite) in self.sprites.iter_mut().enumerate().take(self.shown) {
+            let (a, b) = (points[i], points[i + 1]);
+            let d = b - a;
+            let mid = (a + b) * 0.5;
+            sprite.set_pos(mid.x / scaler, mid.y / scaler);
+            sprite.set_scale(width, d.norm() / height);
+            sprite.set_rot(d.y.atan2(d.x) - f32::consts::FRAC_PI_2);
+        }
+    }
+}
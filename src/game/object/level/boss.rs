@@ -0,0 +1,37 @@
+use game::object::level::action::Action;
+
+// [bosses.cirno]
+// sprite = "cirno"
+//
+// [[bosses.cirno.phases]]
+// health = 200
+// time_limit = 30.0
+// loop = 4.0
+// spell = { name = "Icicle Fall", bonus = 50000 }
+// action = [{ type = "bullets", bullet_id = "basic_straight", delay = 1.0, pattern = { ... } }]
+
+#[derive(Clone, Debug)]
+pub struct Boss {
+    pub sprite: usize,
+    /// Fought in order, the boss is defeated once the last one ends
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Phase {
+    pub health: usize,
+    /// The phase ends without a capture once this many seconds have passed
+    pub time_limit: f32,
+    /// Bullet script of the phase
+    pub actions: Vec<Action>,
+    /// Restarts the script this many seconds after it started
+    pub loop_time: Option<f32>,
+    pub spell: Option<Spell>,
+}
+
+/// Named spell card, captured by beating it in time without dying or bombing
+#[derive(Clone, Debug)]
+pub struct Spell {
+    pub name: String,
+    pub bonus: u64,
+}
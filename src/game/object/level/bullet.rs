@@ -2,12 +2,20 @@
 // sprite = 10
 // radius = 5
 // behavior = "straight"
+// orient = true
+//
+// [bullets.sweeping_laser]
+// sprite = 12
+// laser = { type = "straight", length = 300, width = 12, warmup = 1.0, duration = 2.0, fade = 0.3, sweep = 30.0 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Bullet {
     pub sprite: usize,
     pub behavior: Behavior,
-    pub damage: usize,
+    /// Rotate the sprite and hitbox to face the direction of travel
+    pub orient: bool,
+    /// Fired as a laser instead of a regular bullet
+    pub laser: Option<Laser>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -15,3 +23,24 @@ pub enum Behavior {
     Straight,
     Deaccelerate(f32, f32),
 }
+
+/// Lasers telegraph as a thin line for `warmup` seconds, are lethal for `duration`
+/// seconds and then shrink away over `fade` seconds
+#[derive(Clone, Copy, Debug)]
+pub struct Laser {
+    pub kind: LaserKind,
+    pub length: f32,
+    /// Width of the beam and its hitbox while lethal
+    pub width: f32,
+    pub warmup: f32,
+    pub duration: f32,
+    pub fade: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LaserKind {
+    /// Beam fixed at its origin, rotating by the given degrees per second while lethal
+    Straight(f32),
+    /// Moving head trailed by its path, turning by the given degrees per second
+    Curvy(f32),
+}
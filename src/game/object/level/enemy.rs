@@ -1,9 +1,24 @@
 // [enemies.basic_cuve]
 // sprite = 1
 // health = 10
+// points = 100
+// drops = { point = 2, power = 1 }
 #[derive(Copy, Clone, Debug)]
 pub struct Enemy {
     pub sprite: usize,
     pub health: usize,
-    pub damage: usize,
+    /// Score for destroying the enemy
+    pub points: u64,
+    pub drops: Drops,
+}
+
+/// Items released when the enemy is destroyed
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Drops {
+    pub point: usize,
+    pub power: usize,
+    /// Pieces towards an extra life
+    pub life: usize,
+    /// Pieces towards an extra bomb
+    pub bomb: usize,
 }
@@ -5,6 +5,8 @@ pub mod spawn;
 pub mod enemy;
 pub mod bullet;
 pub mod point;
+pub mod player;
+pub mod boss;
 
 pub use self::point::Point;
 
@@ -14,27 +16,36 @@ use engine::util;
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::Event;
-use engine::scene::Registry;
+use engine::scene::{Registry, Handle};
 use engine::util::HashMap;
 
 use game::object::Object;
 use game::object::player::Player;
 use game::object::enemy::Enemy;
+use game::object::boss::Boss;
 use game::object::widget::LevelStatus;
+use game::object::bullet::BulletManager;
 use game::event::Event as CEvent;
+use game::resource::Score;
 use self::spawn::{Spawn, SpawnType};
+use self::player::Player as PlayerInfo;
+
+/// Level time between checkpoint requests
+const CHECKPOINT_INTERVAL: f32 = 5.0;
 
 /// Top level game controller
 pub struct Level {
     ev: EventComp<Object>,
     world: WorldComp<Object>,
+    res: ResourceComp,
     events: HashMap<String, Vec<LevelEvent>>,
+    player: PlayerInfo,
     waiting_events: HashMap<usize, LevelEvent>,
     waiting_spawns: HashMap<usize, Spawn>,
     active_spawns: Vec<Spawn>,
     ev_reg: Registry,
-    fast_forward: f32,
     ctime: f32,
+    next_checkpoint: f32,
 }
 
 #[derive(Clone, Debug)]
@@ -46,36 +57,51 @@ pub struct LevelEvent {
 }
 
 impl Level {
-    pub fn new(engine: &Engine<Object>, level: HashMap<String, Vec<LevelEvent>>) -> Object {
-        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
-        let e = EventComp::new(w.id, engine.events.clone());
-        Object::Level(Level {
-            ev: e,
-            ev_reg: Registry::new(),
-            events: level,
-            waiting_events: util::hashmap(),
-            waiting_spawns: util::hashmap(),
-            active_spawns: Vec::new(),
-            world: w,
-            fast_forward: 0.0,
-            ctime: 0.001,
-        })
+    pub fn new(engine: &Engine<Object>, level: HashMap<String, Vec<LevelEvent>>, player: PlayerInfo) -> Object {
+        Object::Level(Level::build(engine, level, player))
     }
 
-    pub fn new_at_nearest_time(engine: &Engine<Object>, level: HashMap<String, Vec<LevelEvent>>, time: f32) -> Object {
-        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
+    fn build(engine: &Engine<Object>, level: HashMap<String, Vec<LevelEvent>>, player: PlayerInfo) -> Level {
+        let w = WorldCompBuilder::new(engine)
+            .with_alias(String::from("level"))
+            .with_tag(String::from("level"))
+            .build();
         let e = EventComp::new(w.id, engine.events.clone());
-        Object::Level(Level {
+        Level {
             ev: e,
             ev_reg: Registry::new(),
+            res: ResourceComp::new(engine),
             events: level,
+            player: player,
             waiting_events: util::hashmap(),
             waiting_spawns: util::hashmap(),
             active_spawns: Vec::new(),
             world: w,
-            fast_forward: time,
             ctime: 0.001,
-        })
+            next_checkpoint: CHECKPOINT_INTERVAL,
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let events = self.events.clone();
+        let player = self.player;
+        let waiting_events = self.waiting_events.clone();
+        let waiting_spawns = self.waiting_spawns.clone();
+        let active_spawns = self.active_spawns.clone();
+        let ev_reg = self.ev_reg.clone();
+        let timers = self.ev.timers();
+        let (ctime, next_checkpoint) = (self.ctime, self.next_checkpoint);
+        Some(Box::new(move |engine| {
+            let mut level = Level::build(engine, events.clone(), player);
+            level.waiting_events = waiting_events.clone();
+            level.waiting_spawns = waiting_spawns.clone();
+            level.active_spawns = active_spawns.clone();
+            level.ev_reg = ev_reg.clone();
+            level.ev.set_timers(timers.clone());
+            level.ctime = ctime;
+            level.next_checkpoint = next_checkpoint;
+            Object::Level(level)
+        }))
     }
 
     fn event_finished(&mut self, id: String) {
@@ -93,15 +119,13 @@ impl Level {
 
     fn handle_level_event(&mut self, evt: LevelEvent) {
         println!("Level event {} triggered", evt.name);
-        if self.ctime > self.fast_forward {
-            for spawn in evt.spawns {
-                if spawn.repeat > 0 {
-                    let wid = self.ev_reg.get_id();
-                    self.ev.set_repeating_timer_with_class(wid, spawn.repeat_delay, 2);
-                    self.waiting_spawns.insert(wid, spawn.clone());
-                }
-                self.active_spawns.push(spawn.clone());
+        for spawn in evt.spawns {
+            if spawn.repeat > 0 {
+                let wid = self.ev_reg.get_id();
+                self.ev.set_repeating_timer_with_class(wid, spawn.repeat_delay, 2);
+                self.waiting_spawns.insert(wid, spawn.clone());
             }
+            self.active_spawns.push(spawn.clone());
         }
         self.event_finished(evt.name);
     }
@@ -109,6 +133,10 @@ impl Level {
     fn handle_update(&mut self, t: f32) {
         self.ctime += t;
         self.ev.update(t);
+        if self.ctime >= self.next_checkpoint {
+            self.ev.checkpoint(self.ctime);
+            self.next_checkpoint += CHECKPOINT_INTERVAL;
+        }
         let mut done_pats = Vec::new();
         for (i, ref mut spawn) in self.active_spawns.iter_mut().enumerate() {
             let ref mut pat = spawn.pattern;
@@ -121,6 +149,11 @@ impl Level {
                         let paths = spawn.paths.clone();
                         self.ev.create_entity(Box::new(move |engine| Enemy::new(engine, info, pos, paths.clone())));
                     }
+                    SpawnType::Boss(ref b_info) => {
+                        let info = b_info.clone();
+                        let pos = pos + spawn.location;
+                        self.ev.create_entity(Box::new(move |engine| Boss::new(engine, info.clone(), pos)));
+                    }
                     SpawnType::Player => {
                         // Spawn the palyer
                         panic!("For the time being please do not spawn the player with the level files!")
@@ -146,9 +179,13 @@ impl Level {
         match *e {
             Event::Spawn => {
                 println!("Spawned Level!");
+                // Every play starts from a clean score
+                self.res.set(Score::default());
                 self.event_finished(String::from("start"));
-                self.ev.create_entity(Box::new(|engine| Player::new(engine)));
+                let player = self.player;
+                self.ev.create_entity(Box::new(move |engine| Player::new(engine, player)));
                 self.ev.create_entity(Box::new(|engine| LevelStatus::new(engine)));
+                self.ev.create_entity(Box::new(|engine| BulletManager::new(engine)));
             }
             Event::Update(t) => {
                 self.handle_update(t);
@@ -176,7 +213,9 @@ impl Level {
                 }
             }
             Event::Custom(ref cev) => {
-                self.handle_cevent(cev.downcast_ref::<CEvent>().unwrap());
+                if let Some(e) = cev.get::<CEvent>() {
+                    self.handle_cevent(e);
+                }
             }
             _ => {}
         };
@@ -184,11 +223,14 @@ impl Level {
 
     fn handle_cevent(&mut self, e: &CEvent) {
         match *e {
+            CEvent::EnemyKilled(_) => {
+                self.res.update(|s: &mut Score| s.kills += 1);
+            }
             _ => { }
         }
     }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
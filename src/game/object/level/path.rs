@@ -5,7 +5,7 @@ use engine::util::ToCartesian;
 
 // TODO: Write tests - this code is complicated and almost certaintly error prone
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Path {
     Arc(Arc),
     Curve(Curve),
@@ -27,14 +27,14 @@ impl RotationDirection {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Fixed {
     time: f32,
     pos: Vector2<f32>,
     actions: Vec<Action>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Arc {
     center: Vector2<f32>,
     current_pos: Vector2<f32>,
@@ -45,7 +45,7 @@ pub struct Arc {
     actions: Vec<Action>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Curve {
     points: Vec<Point2<f32>>,
     current_pos: Vector2<f32>,
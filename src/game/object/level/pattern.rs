@@ -66,8 +66,9 @@ impl Pattern {
         self.pos_fetcher = Some(fetcher);
     }
 
-    /// Consumes a portion of the arc and emits translation and velocity vectors of the object
-    pub fn next(&mut self, dt: f32) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    /// Consumes a portion of the arc and emits translation and velocity vectors of the object,
+    /// along with the angle in degrees it was fired at
+    pub fn next(&mut self, dt: f32) -> Vec<(Vector2<f32>, Vector2<f32>, f32)> {
         self.update(dt);
         let mut res = Vec::new();
         for pattern in self.active_patterns.iter_mut() {
@@ -81,7 +82,7 @@ impl Pattern {
                 let angle = pattern.cur_angle + wobble_angle;
                 pattern.cur_angle += (self.stop_angle - self.start_angle) / self.amount as f32;
                 let base = Vector2::new(1.0, angle.to_radians()).to_cartesian();
-                res.push((base * self.radius, base * self.speed))
+                res.push((base * self.radius, base * self.speed, angle))
             }
         }
         res
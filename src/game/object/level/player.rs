@@ -0,0 +1,65 @@
+// [player]
+// character = "sakuya"
+// lives = 3
+// shot_damage = 1
+// bombs = 3
+//
+// [player.characters.sakuya.bomb]
+// type = "radius"
+// radius = 120
+// damage = 2
+// duration = 2.0
+// sprite = "bomb"
+
+/// Player settings of a level
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    /// Lives the player starts with, the game is over once the last one is lost
+    pub lives: usize,
+    /// Damage each shot deals to enemies
+    pub shot_damage: usize,
+    /// Bombs the player starts with
+    pub bombs: usize,
+    /// Bomb of the chosen character, characters only differ in their bomb for now
+    pub bomb: Bomb,
+}
+
+impl Default for Player {
+    fn default() -> Player {
+        Player {
+            lives: 3,
+            shot_damage: 1,
+            bombs: 3,
+            bomb: Bomb::default(),
+        }
+    }
+}
+
+/// Clears enemy bullets and damages enemies in its area while it lasts
+#[derive(Clone, Copy, Debug)]
+pub struct Bomb {
+    pub kind: BombKind,
+    /// Damage dealt to every enemy in the area each tenth of a second
+    pub damage: usize,
+    pub duration: f32,
+    pub sprite: Option<usize>,
+}
+
+impl Default for Bomb {
+    fn default() -> Bomb {
+        Bomb {
+            kind: BombKind::Radius(120.0),
+            damage: 2,
+            duration: 2.0,
+            sprite: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BombKind {
+    /// Circle of the given radius around where the bomb was used
+    Radius(f32),
+    /// The whole playfield
+    Screen,
+}
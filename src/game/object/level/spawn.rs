@@ -3,6 +3,7 @@ use nalgebra::Vector2;
 use game::object::level::path::PathBuilder;
 use game::object::level::pattern::{Angle, Pattern, PatternBuilder};
 use game::object::level::enemy::Enemy;
+use game::object::level::boss::Boss;
 
 #[derive(Clone, Debug)]
 pub struct Spawn {
@@ -22,11 +23,21 @@ impl Spawn {
             .pattern(pattern)
             .build(&location, &Vector2::new(0.0, 0.0))
     }
+
+    pub fn boss(boss: Boss, location: Vector2<f32>) -> Spawn {
+        let pattern = PatternBuilder::new().fixed_angle(Angle::Fixed(270.0));
+        SpawnBuilder::new()
+            .spawn_type(SpawnType::Boss(boss))
+            .pattern(pattern)
+            .location(location)
+            .build(&location, &Vector2::new(0.0, 0.0))
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum SpawnType {
     Enemy(Enemy),
+    Boss(Boss),
     Player,
 }
 
@@ -11,14 +11,17 @@ use engine::Engine;
 use engine::entity::component::*;
 use engine::event::{Event, InputState};
 use engine::util;
+use engine::scene::Handle;
 use game::asset::level::Events;
+use game::object::level::player::Player;
 
 struct State {
     selected_level: i32,
     asset_files: Vec<DirEntry>,
     level_start_time: i32,
     level_time_max: i32,
-    level: Result<Events, String>,
+    level: Result<(Events, Player), String>,
+    level_path: String,
 }
 
 impl State {
@@ -29,7 +32,8 @@ impl State {
             level_start_time: 0,
             level_time_max: 100,
             asset_files: files,
-            level: Ok(util::hashmap()),
+            level: Ok((util::hashmap(), Player::default())),
+            level_path: String::new(),
         }
     }
 }
@@ -111,10 +115,11 @@ impl MainMenu {
                         if ui.list_box(im_str!("Level file"), &mut renderer.state.selected_level, &file_names[..], 5) {
                             use game::asset::level;
                             let file = renderer.state.asset_files[renderer.state.selected_level as usize].file_name().into_string().unwrap();
-                            renderer.state.level = match level::load_level_file(graphics, &(String::from("assets/levels/") + &file)) {
-                                Ok((_, _, _, events)) => {
+                            renderer.state.level_path = String::from("assets/levels/") + &file;
+                            renderer.state.level = match level::load_level_file(graphics, &renderer.state.level_path) {
+                                Ok((_, _, _, _, events, player)) => {
                                     renderer.state.level_time_max = get_max_level_time(&events);
-                                    Ok(events)  
+                                    Ok((events, player))
                                 },
                                 Err(s) => Err(s),
                             };
@@ -123,13 +128,12 @@ impl MainMenu {
                             ui.slider_int(im_str!("Start time"), &mut renderer.state.level_start_time, 0, renderer.state.level_time_max).build();
                             if ui.small_button(im_str!("Start")) {
                                 // Actually load level
-                                let e = Event::Custom(
-                                    Box::new(
-                                        CEvent::LevelStart(
-                                            renderer.state.level.clone().unwrap(),
-                                            renderer.state.level_start_time as i32
-                                        )
-                                    )
+                                let (events, player) = renderer.state.level.clone().unwrap();
+                                let e = CEvent::LevelStart(
+                                    events,
+                                    player,
+                                    renderer.state.level_start_time as i32,
+                                    renderer.state.level_path.clone()
                                 );
                                 Some(e)
                             } else { None }
@@ -147,12 +151,16 @@ impl MainMenu {
             renderer.render(ui);
             if let Some(e) = ev {
                 let cid = self.world.find_aliased_entity_id(&String::from("controller")).unwrap();
-                self.ev.dispatch_to(cid, e);
+                self.ev.dispatch_custom_to(cid, e);
             }
         }
     }
 
-    pub fn id(&self) -> usize {
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        None
+    }
+
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
@@ -3,6 +3,8 @@ pub mod boss;
 pub mod bullet;
 pub mod controller;
 pub mod enemy;
+pub mod item;
+pub mod laser;
 pub mod level;
 pub mod menu;
 pub mod mouse;
@@ -18,7 +20,11 @@ pub enum Object {
     Player(player::Player),
     PlayerBullet(player::Bullet),
     Enemy(enemy::Enemy),
-    Bullet(bullet::Bullet),
+    BulletManager(bullet::BulletManager),
+    Laser(laser::Laser),
+    Bomb(bomb::Bomb),
+    Boss(boss::Boss),
+    Item(item::Item),
 }
 
-impl_entity_enum!(Object, Player, PlayerBullet, MainMenu, Mouse, Controller, Level, Enemy, Bullet, LevelStatus);
+impl_entity_enum!(Object, Player, PlayerBullet, MainMenu, Mouse, Controller, Level, Enemy, BulletManager, Laser, Bomb, Boss, Item, LevelStatus);
@@ -7,6 +7,7 @@ use game::object::Object;
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::{Event, InputState};
+use engine::scene::Handle;
 
 pub struct Mouse {
     world: WorldComp<Object>,
@@ -48,7 +49,11 @@ impl Mouse {
         };
     }
 
-    pub fn id(&self) -> usize {
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        None
+    }
+
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
@@ -1,26 +1,71 @@
 use nalgebra::Vector2;
 use glium::glutin::VirtualKeyCode;
 use std::rc::Rc;
+use std::f32;
 
 use engine::Engine;
 use engine::entity::component::*;
 use engine::event::{Event, InputState};
+use engine::scene::{CullPolicy, Handle};
+use game::event::Event as CEvent;
 use game::object::Object;
+use game::object::bomb::Bomb;
+use game::object::item::ItemKind;
+use game::object::level::player::Player as PlayerInfo;
+use game::physics::{ENEMY, ENEMY_BULLET, PLAYER_BULLET};
+use game::resource::{PlayerState, Score};
 
-pub static mut PLAYER_POSITION: Vector2<f32> = Vector2{ x: 0.0, y: 0.0 };
-pub static mut PLAYER_ID: usize = 0;
+/// Length of the death sequence before the player respawns
+const DEATH_TIME: f32 = 1.0;
+/// Invulnerability after respawning
+const INVULNERABLE_TIME: f32 = 3.0;
+/// The sprite is shown and hidden in turns this long while invulnerable
+const BLINK_INTERVAL: f32 = 0.1;
+/// Distance of the respawn point from the bottom of the playfield
+const RESPAWN_HEIGHT: f32 = 80.0;
+/// Time after being hit during which bombing cancels the death
+const DEATHBOMB_WINDOW: f32 = 0.15;
+/// Score for every bullet grazed
+const GRAZE_POINTS: u64 = 50;
+/// Power stops going up past this
+const MAX_POWER: usize = 128;
+/// Power needed for every extra point of shot damage
+const POWER_PER_LEVEL: usize = 32;
+/// Life or bomb pieces which make up a whole extra
+pub const PIECES_PER_EXTRA: usize = 3;
 
 pub struct Player {
     pg: PGComp,
     ev: EventComp<Object>,
     world: WorldComp<Object>,
+    res: ResourceComp,
     slowdown: f32,
+    info: PlayerInfo,
+    lives: usize,
+    bombs: usize,
+    /// Time left to deathbomb after being hit
+    hit: f32,
+    /// Time left in the death sequence
+    dying: f32,
+    /// Time left until the player can be hit again
+    invulnerable: f32,
+    /// Time left until the current bomb runs out
+    bombing: f32,
+    power: usize,
+    life_pieces: usize,
+    bomb_pieces: usize,
 }
 
 impl Player {
-    pub fn new(engine: &Engine<Object>) -> Object {
-        let w = WorldCompBuilder::new(engine).with_alias(String::from("player")).build();
-        unsafe { PLAYER_ID = w.id };
+    pub fn new(engine: &Engine<Object>, info: PlayerInfo) -> Object {
+        Object::Player(Player::build(engine, info, info.lives, info.bombs))
+    }
+
+    fn build(engine: &Engine<Object>, info: PlayerInfo, lives: usize, bombs: usize) -> Player {
+        let w = WorldCompBuilder::new(engine)
+            .with_alias(String::from("player"))
+            .with_tag(String::from("level"))
+            .build();
         let g = GraphicsComp::new(engine.graphics.clone(), 1);
         let e = EventComp::new(w.id, engine.events.clone());
 
@@ -33,12 +78,59 @@ impl Player {
         let mut pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
         pg.screen_lock((25.0, 50.0));
 
-        Object::Player(Player {
+        let res = ResourceComp::new(engine);
+        res.set(PlayerState {
+            id: w.id,
+            pos: pg.get_vpos(),
+            lives: lives,
+            bombs: bombs,
+            power: 0,
+            life_pieces: 0,
+            bomb_pieces: 0,
+        });
+        Player {
             pg: pg,
             ev: e,
             world: w,
+            res: res,
             slowdown: 1.0,
-        })
+            info: info,
+            lives: lives,
+            bombs: bombs,
+            hit: 0.0,
+            dying: 0.0,
+            invulnerable: 0.0,
+            bombing: 0.0,
+            power: 0,
+            life_pieces: 0,
+            bomb_pieces: 0,
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let pos = self.pg.get_pos();
+        let velocity = self.pg.velocity;
+        let slowdown = self.slowdown;
+        let (info, lives, bombs) = (self.info, self.lives, self.bombs);
+        let (hit, dying, invulnerable, bombing) = (self.hit, self.dying, self.invulnerable, self.bombing);
+        let (power, life_pieces, bomb_pieces) = (self.power, self.life_pieces, self.bomb_pieces);
+        let timers = self.ev.timers();
+        Some(Box::new(move |engine| {
+            let mut player = Player::build(engine, info, lives, bombs);
+            player.pg.teleport(pos);
+            player.pg.velocity = velocity;
+            player.slowdown = slowdown;
+            player.hit = hit;
+            player.dying = dying;
+            player.invulnerable = invulnerable;
+            player.bombing = bombing;
+            player.power = power;
+            player.life_pieces = life_pieces;
+            player.bomb_pieces = bomb_pieces;
+            player.update_state();
+            player.ev.set_timers(timers.clone());
+            Object::Player(player)
+        }))
     }
 
     pub fn get_pos(&self) -> Vector2<f32> {
@@ -48,19 +140,39 @@ impl Player {
 
     pub fn handle_event(&mut self, e: Rc<Event>) {
         match *e {
-            Event::Spawn => {
+            Event::Spawn | Event::Restore => {
                 self.ev.subscribe(Event::KeyInput(InputState::Pressed, VirtualKeyCode::A));
-                let _ = self.world.get_entity(&100);
             }
             Event::Update(t) => {
-                self.pg.update(t);
                 self.ev.update(t);
-                unsafe {
-                    PLAYER_POSITION = self.pg.get_vpos();
-                }
+                self.update(t);
+                let pos = self.pg.get_vpos();
+                self.res.update(|p: &mut PlayerState| p.pos = pos);
             }
             Event::Render => {
-                self.pg.render();
+                if self.is_visible() {
+                    self.pg.render();
+                } else {
+                    self.pg.hide();
+                }
+            }
+            Event::CollisionBegin(_, ref data) |
+            Event::Collision(_, ref data) if self.is_vulnerable() => {
+                let group = data.other_object.group;
+                if group == ENEMY_BULLET || group == ENEMY {
+                    self.hit = DEATHBOMB_WINDOW;
+                }
+            }
+            Event::Proximity(_, ref data) if data.other_object.group == ENEMY_BULLET && self.is_vulnerable() => {
+                self.res.update(|s: &mut Score| {
+                    s.grazes += 1;
+                    s.points += GRAZE_POINTS;
+                });
+            }
+            Event::Custom(ref cev) => {
+                if let Some(&CEvent::ItemCollected(kind, value)) = cev.get::<CEvent>() {
+                    self.collect(kind, value);
+                }
             }
             Event::KeyInput(InputState::Pressed, VirtualKeyCode::Up) |
             Event::KeyInput(InputState::Released, VirtualKeyCode::Down) => {
@@ -96,48 +208,177 @@ impl Player {
             Event::KeyInput(InputState::Released, VirtualKeyCode::Z) => {
                 self.ev.remove_timer(1);
             }
+            Event::KeyInput(InputState::Pressed, VirtualKeyCode::X) => {
+                self.bomb();
+            }
             _ => {}
         };
     }
 
+    fn update(&mut self, dt: f32) {
+        if self.dying > 0.0 {
+            self.dying -= dt;
+            if self.dying <= 0.0 {
+                self.respawn();
+            } else {
+                // Spin and shrink away until respawning
+                let left = self.dying / DEATH_TIME;
+                self.pg.set_scale(left, left);
+                self.pg.set_rot((1.0 - left) * 4.0 * f32::consts::PI);
+            }
+            return;
+        }
+        if self.hit > 0.0 {
+            self.hit -= dt;
+            if self.hit <= 0.0 {
+                self.die();
+                return;
+            }
+        }
+        if self.invulnerable > 0.0 {
+            self.invulnerable -= dt;
+        }
+        if self.bombing > 0.0 {
+            self.bombing -= dt;
+        }
+        self.pg.update(dt);
+    }
+
+    fn is_vulnerable(&self) -> bool {
+        self.hit <= 0.0 && self.dying <= 0.0 && self.invulnerable <= 0.0
+    }
+
+    /// Uses a bomb if one is left, also saves the player if they were just hit
+    fn bomb(&mut self) {
+        if self.bombs == 0 || self.bombing > 0.0 || self.dying > 0.0 {
+            return;
+        }
+        self.bombs -= 1;
+        let bombs = self.bombs;
+        self.res.update(|p: &mut PlayerState| p.bombs = bombs);
+        self.res.update(|s: &mut Score| s.bombs_used += 1);
+        self.hit = 0.0;
+        self.bombing = self.info.bomb.duration;
+        self.invulnerable = self.invulnerable.max(self.info.bomb.duration);
+        let (info, pos) = (self.info.bomb, self.pg.get_vpos());
+        self.ev.create_entity(Box::new(move |engine| Bomb::new(engine, info, pos)));
+    }
+
+    /// Point and power items score their value, pieces add up to extra lives and bombs
+    fn collect(&mut self, kind: ItemKind, value: u64) {
+        self.res.update(|s: &mut Score| s.points += value);
+        match kind {
+            ItemKind::Point => {}
+            ItemKind::Power => {
+                self.power = (self.power + 1).min(MAX_POWER);
+            }
+            ItemKind::LifePiece => {
+                self.life_pieces += 1;
+                if self.life_pieces >= PIECES_PER_EXTRA {
+                    self.life_pieces = 0;
+                    self.lives += 1;
+                }
+            }
+            ItemKind::BombPiece => {
+                self.bomb_pieces += 1;
+                if self.bomb_pieces >= PIECES_PER_EXTRA {
+                    self.bomb_pieces = 0;
+                    self.bombs += 1;
+                }
+            }
+        }
+        self.update_state();
+    }
+
+    fn update_state(&self) {
+        let (lives, bombs) = (self.lives, self.bombs);
+        let (power, life_pieces, bomb_pieces) = (self.power, self.life_pieces, self.bomb_pieces);
+        self.res.update(|p: &mut PlayerState| {
+            p.lives = lives;
+            p.bombs = bombs;
+            p.power = power;
+            p.life_pieces = life_pieces;
+            p.bomb_pieces = bomb_pieces;
+        });
+    }
+
+    fn is_visible(&self) -> bool {
+        self.invulnerable <= 0.0 || (self.invulnerable / BLINK_INTERVAL) as usize % 2 == 0
+    }
+
+    fn die(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        let lives = self.lives;
+        self.res.update(|p: &mut PlayerState| p.lives = lives);
+        self.res.update(|s: &mut Score| s.deaths += 1);
+        self.hit = 0.0;
+        if lives == 0 {
+            if let Some(cid) = self.world.find_aliased_entity_id(&String::from("controller")) {
+                self.ev.dispatch_custom_to(cid, CEvent::GameOver);
+            }
+        }
+        self.dying = DEATH_TIME;
+    }
+
+    fn respawn(&mut self) {
+        self.dying = 0.0;
+        self.invulnerable = INVULNERABLE_TIME;
+        self.pg.set_scale(1.0, 1.0);
+        self.pg.set_rot(0.0);
+        let bottom = -self.pg.scaler + RESPAWN_HEIGHT;
+        self.pg.teleport((0.0, bottom));
+    }
+
     fn shoot_bullet(&mut self) {
+        if self.dying > 0.0 {
+            return;
+        }
         let pos = self.pg.get_pos();
-        self.ev.create_entity(Box::new(move |engine| Bullet::new_at_pos(engine, pos)));
+        let damage = self.info.shot_damage + self.power / POWER_PER_LEVEL;
+        self.ev.create_entity(Box::new(move |engine| Bullet::new_at_pos(engine, pos, damage)));
     }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
 
 pub struct Bullet {
+    pub damage: usize,
     pg: PGComp,
     ev: EventComp<Object>,
     world: WorldComp<Object>,
 }
 
 impl Bullet {
-    pub fn new_at_pos(engine: &Engine<Object>, pos: (f32, f32)) -> Object {
+    pub fn new_at_pos(engine: &Engine<Object>, pos: (f32, f32), damage: usize) -> Object {
         let mut g = GraphicsComp::new(engine.graphics.clone(), 2);
-        let w = WorldCompBuilder::new(engine).build();
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
         let e = EventComp::new(w.id, engine.events.clone());
         let scaler = engine.scene.physics.scaler;
         let p = PhysicsComp::new(w.id,
                                  0,
                                  Vector2::new(pos.0, pos.1),
                                  engine.graphics.borrow().get_sprite_shape(&2).unwrap(),
-                                 8,
+                                 PLAYER_BULLET,
                                  &engine.scene);
         g.translate(pos.0 / scaler, pos.1 / scaler);
         let mut pg = PGComp::new(g, vec![p], engine.scene.physics.clone());
         pg.velocity = Vector2::new(0.0, 100.0);
+        pg.set_cull(CullPolicy::Despawn);
         Object::PlayerBullet(Bullet {
+            damage: damage,
             pg: pg,
             ev: e,
             world: w,
         })
     }
 
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        let (pos, damage) = (self.pg.get_pos(), self.damage);
+        Some(Box::new(move |engine| Bullet::new_at_pos(engine, pos, damage)))
+    }
+
     pub fn handle_event(&mut self, e: Rc<Event>) {
         match *e {
             Event::Spawn => {}
@@ -147,11 +388,15 @@ impl Bullet {
             Event::Render => {
                 self.pg.render();
             }
+            Event::CollisionBegin(id, ref data) if data.other_object.group == ENEMY => {
+                self.ev.dispatch_custom_to(id, CEvent::Damage(self.damage));
+                self.ev.destroy_self();
+            }
             _ => {}
         };
     }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
@@ -1,14 +1,20 @@
 use imgui::*;
-use glium::glutin::{MouseButton, MouseScrollDelta, TouchPhase};
+use glium::glutin::{MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode};
 use std::rc::Rc;
 use clock_ticks;
 
 use game::object::Object;
-use game::object::bullet::BULLET_COUNT;
-use game::object::enemy::ENEMY_COUNT;
+use game::object::player::PIECES_PER_EXTRA;
+use game::resource::{BossState, Counts, PlayerState, Score};
 use engine::event::{Event, InputState};
 use engine::Engine;
 use engine::entity::component::*;
+use engine::scene::Handle;
+
+/// Time scales cycled through with T, slower speeds help with dense patterns
+const TIME_SCALES: [f32; 3] = [1.0, 0.5, 0.25];
+/// Characters the boss health bar is drawn with
+const HEALTH_BAR_WIDTH: usize = 30;
 
 struct State {
     start_time: u64,
@@ -37,6 +43,7 @@ impl State {
 
 pub struct LevelStatus {
     ev: EventComp<Object>,
+    res: ResourceComp,
     world: WorldComp<Object>,
     menu: MenuComp,
     state: State,
@@ -44,25 +51,49 @@ pub struct LevelStatus {
 
 impl LevelStatus {
     pub fn new(engine: &Engine<Object>) -> Object {
-        let w = WorldCompBuilder::new(engine).build();
+        let w = WorldCompBuilder::new(engine).with_tag(String::from("level")).build();
         let e = EventComp::new(w.id, engine.events.clone());
         let m = MenuComp::new(engine);
         Object::LevelStatus(LevelStatus {
             ev: e,
+            res: ResourceComp::new(engine),
             world: w,
             menu: m,
             state: State::new(),
         })
     }
 
+    pub fn snapshot(&self) -> Option<Box<Fn(&Engine<Object>) -> Object>> {
+        Some(Box::new(|engine| LevelStatus::new(engine)))
+    }
+
     pub fn handle_event(&mut self, e: Rc<Event>) {
         match *e {
-            Event::Spawn => {
+            Event::Spawn | Event::Restore => {
                 self.ev.subscribe(Event::RenderMenu);
                 self.ev.subscribe(Event::MouseMove((0.0, 0.0)));
                 self.ev.subscribe(Event::MouseInput(InputState::Released, MouseButton::Left));
                 self.ev.subscribe(Event::MouseScroll(MouseScrollDelta::LineDelta(0.0, 0.0),
                 TouchPhase::Moved));
+                self.ev.subscribe(Event::KeyInput(InputState::Pressed, VirtualKeyCode::P));
+            }
+            Event::KeyInput(InputState::Pressed, VirtualKeyCode::P) => {
+                if self.ev.is_paused() {
+                    self.ev.resume();
+                } else {
+                    self.ev.pause();
+                }
+            }
+            Event::KeyInput(InputState::Pressed, VirtualKeyCode::N) => {
+                self.ev.step();
+            }
+            Event::KeyInput(InputState::Pressed, VirtualKeyCode::T) => {
+                let scale = self.ev.time_scale();
+                let next = match TIME_SCALES.iter().position(|s| *s == scale) {
+                    Some(i) => TIME_SCALES[(i + 1) % TIME_SCALES.len()],
+                    None => TIME_SCALES[0],
+                };
+                self.ev.set_time_scale(next);
             }
             Event::MouseMove(pos) => {
                 self.menu.set_mouse_pos(pos);
@@ -85,6 +116,12 @@ impl LevelStatus {
 
     fn render_ui(&mut self) {
         let now = clock_ticks::precise_time_ms();
+        let paused = self.ev.is_paused();
+        let time_scale = self.ev.time_scale();
+        let counts = self.res.get_or_default::<Counts>();
+        let score = self.res.get_or_default::<Score>();
+        let player = self.res.get_or_default::<PlayerState>();
+        let boss = self.res.get_or_default::<BossState>();
         let mut renderer = self.menu.get_renderer(&mut self.state);
 
         renderer.state.frames_drawn += 1;
@@ -115,15 +152,48 @@ impl LevelStatus {
                         .scale_min(15.0)
                         .scale_max(25.0)
                         .build();
-                    ui.text(im_str!("enemies: {:?}", unsafe { ENEMY_COUNT }));
-                    ui.text(im_str!("bullets: {:?}", unsafe { BULLET_COUNT }));
+                    ui.text(im_str!("score: {:?}", score.points));
+                    ui.text(im_str!("lives: {:?} ({}/{})", player.lives, player.life_pieces, PIECES_PER_EXTRA));
+                    ui.text(im_str!("bombs: {:?} ({}/{})", player.bombs, player.bomb_pieces, PIECES_PER_EXTRA));
+                    ui.text(im_str!("power: {:?}", player.power));
+                    ui.text(im_str!("enemies: {:?}", counts.enemies));
+                    ui.text(im_str!("bullets: {:?}", counts.bullets));
+                    ui.text(im_str!("grazes: {:?}", score.grazes));
+                    ui.text(im_str!("kills: {:?}", score.kills));
+                    if boss.active {
+                        ui.separator();
+                        ui.text(im_str!("boss [{}]", health_bar(boss.health, boss.max_health)));
+                        ui.text(im_str!("time: {:.1}  phases left: {}", boss.time_left, boss.phases_left));
+                        if let Some(ref spell) = boss.spell {
+                            ui.text(im_str!("spell card: {}", spell));
+                        }
+                        ui.separator();
+                    }
+                    if paused {
+                        ui.text(im_str!("paused (P resume, N step)"));
+                    }
+                    ui.text(im_str!("time scale: {:?} (T to change)", time_scale));
                 });
             ui
         };
         renderer.render(ui);
     }
 
-    pub fn id(&self) -> usize {
+    pub fn id(&self) -> Handle {
         self.world.id
     }
 }
+
+/// Text bar filled in proportion to the health left
+fn health_bar(health: usize, max_health: usize) -> String {
+    let filled = if max_health == 0 {
+        0
+    } else {
+        (health * HEALTH_BAR_WIDTH + max_health - 1) / max_health
+    };
+    let mut bar = String::with_capacity(HEALTH_BAR_WIDTH);
+    for i in 0..HEALTH_BAR_WIDTH {
+        bar.push(if i < filled { '#' } else { '-' });
+    }
+    bar
+}
@@ -1,76 +1,392 @@
-use ncollide_geometry::shape::ShapeHandle2;
-use ncollide_geometry::query::{self, Proximity};
-use nalgebra::{Isometry2, Vector2};
+use ncollide_geometry::shape::{Ball, Cuboid, ShapeHandle2};
+use ncollide_geometry::query::{self, Proximity, Ray};
+use nalgebra::{Isometry2, Norm, Point2, Vector1, Vector2};
+use std::cmp::Ordering;
 use std::rc::Rc;
-use engine::util::{self, HashMap};
+use engine::util::{self, HashMap, HashSet};
 use engine::entity::component::PhysicsData;
-use engine::physics::{Collision, PhysicsEngine};
-use nalgebra as na;
+use engine::physics::{Collision, Contact, PhysicsEngine, QueryHit};
 
 pub type Shape = ShapeHandle2<f32>;
 
+// Note that group 1 = player, 2 = enemy bullet, 4 = enemy, 8 = player bullet, 16 = item, 101 = mouse
+pub const PLAYER: u8 = 1;
+pub const ENEMY_BULLET: u8 = 2;
+pub const ENEMY: u8 = 4;
+pub const PLAYER_BULLET: u8 = 8;
+pub const ITEM: u8 = 16;
+
+/// Default edge length of a broadphase grid cell in world units
+const CELL_SIZE: f32 = 16.0;
+/// Default distance from the player's hitbox within which a bullet counts as grazing
+const GRAZE_RADIUS: f32 = 12.0;
+/// Default speed above which objects are swept from their previous position
+const CCD_SPEED: f32 = 300.0;
+
+/// Which pairs of collision groups are tested against each other, and which of them
+/// also report grazes
+#[derive(Clone)]
+pub struct CollisionMatrix {
+    pairs: Vec<bool>,
+    graze: Vec<bool>,
+    active: Vec<bool>,
+}
+
+impl CollisionMatrix {
+    /// A matrix where no groups collide
+    pub fn new() -> CollisionMatrix {
+        CollisionMatrix {
+            pairs: vec![false; 256 * 256],
+            graze: vec![false; 256 * 256],
+            active: vec![false; 256],
+        }
+    }
+
+    /// Player vs enemies, their bullets and items, enemies vs player bullets
+    pub fn danmaku() -> CollisionMatrix {
+        let mut m = CollisionMatrix::new();
+        m.enable(PLAYER, ENEMY_BULLET);
+        m.enable(PLAYER, ENEMY);
+        m.enable(PLAYER, ITEM);
+        m.enable(ENEMY, PLAYER_BULLET);
+        m.enable_graze(PLAYER, ENEMY_BULLET);
+        m
+    }
+
+    pub fn enable(&mut self, a: u8, b: u8) {
+        self.set(a, b, true);
+    }
+
+    pub fn disable(&mut self, a: u8, b: u8) {
+        self.set(a, b, false);
+    }
+
+    pub fn collides(&self, a: u8, b: u8) -> bool {
+        self.pairs[a as usize * 256 + b as usize]
+    }
+
+    /// Reports objects of the two groups coming within the graze radius of each other
+    pub fn enable_graze(&mut self, a: u8, b: u8) {
+        self.graze[a as usize * 256 + b as usize] = true;
+        self.graze[b as usize * 256 + a as usize] = true;
+        self.update_active();
+    }
+
+    pub fn disable_graze(&mut self, a: u8, b: u8) {
+        self.graze[a as usize * 256 + b as usize] = false;
+        self.graze[b as usize * 256 + a as usize] = false;
+        self.update_active();
+    }
+
+    pub fn grazes(&self, a: u8, b: u8) -> bool {
+        self.graze[a as usize * 256 + b as usize]
+    }
+
+    /// Whether the group grazes anything at all
+    pub fn is_grazing(&self, group: u8) -> bool {
+        let g = group as usize;
+        self.graze[g * 256..(g + 1) * 256].iter().any(|p| *p)
+    }
+
+    /// Whether the group collides with anything at all
+    pub fn is_active(&self, group: u8) -> bool {
+        self.active[group as usize]
+    }
+
+    fn set(&mut self, a: u8, b: u8, value: bool) {
+        self.pairs[a as usize * 256 + b as usize] = value;
+        self.pairs[b as usize * 256 + a as usize] = value;
+        self.update_active();
+    }
+
+    fn update_active(&mut self) {
+        for g in 0..256 {
+            let row = g * 256..(g + 1) * 256;
+            self.active[g] = self.pairs[row.clone()].iter().chain(self.graze[row].iter()).any(|p| *p);
+        }
+    }
+}
+
 pub struct DanmakuPhysics {
     scaler: f32,
-    pid: usize,
     objects: HashMap<usize, Object>,
+    matrix: CollisionMatrix,
+    cell_size: f32,
+    graze_radius: f32,
+    ccd_speed: f32,
+    cells: HashMap<(i32, i32), Vec<(u8, usize)>>,
+    /// Pairs which were within the graze radius last update, so a pass is reported once
+    grazing: HashSet<(usize, usize)>,
 }
 
-// Note that group 1 = player, 2 = enemy bullet, 4 = enemy, 8 = player bullet
 struct Object {
     pos: Vector2<f32>,
+    /// Position at the end of the last update
+    prev: Vector2<f32>,
+    /// Moved fast enough this update to be tested along its whole path
+    swept: bool,
+    rot: f32,
     shape: Shape,
     data: Rc<PhysicsData>,
+    mins: (f32, f32),
+    maxs: (f32, f32),
+}
+
+impl Object {
+    fn isometry(&self) -> Isometry2<f32> {
+        Isometry2::new(self.pos, Vector1::new(self.rot))
+    }
+
+    fn prev_isometry(&self) -> Isometry2<f32> {
+        Isometry2::new(self.prev, Vector1::new(self.rot))
+    }
+
+    fn hit(&self, distance: f32) -> QueryHit<PhysicsData> {
+        QueryHit {
+            id: self.data.entity_id,
+            data: self.data.clone(),
+            distance: distance,
+        }
+    }
 }
 
 impl DanmakuPhysics {
     pub fn new(scaler: f32) -> DanmakuPhysics {
+        DanmakuPhysics::with_matrix(scaler, CollisionMatrix::danmaku())
+    }
+
+    pub fn with_matrix(scaler: f32, matrix: CollisionMatrix) -> DanmakuPhysics {
         DanmakuPhysics {
             scaler: scaler,
             objects: util::hashmap(),
-            pid: 50000,
+            matrix: matrix,
+            cell_size: CELL_SIZE,
+            graze_radius: GRAZE_RADIUS,
+            ccd_speed: CCD_SPEED,
+            cells: util::hashmap(),
+            grazing: util::hashset(),
+        }
+    }
+
+    pub fn set_cell_size(&mut self, size: f32) {
+        self.cell_size = size;
+        self.cells.clear();
+    }
+
+    pub fn set_graze_radius(&mut self, radius: f32) {
+        self.graze_radius = radius;
+    }
+
+    /// Objects moving faster than this are tested from their previous position so
+    /// they can't pass through what they would hit
+    pub fn set_ccd_speed(&mut self, speed: f32) {
+        self.ccd_speed = speed;
+    }
+
+    fn cell_of(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+
+    /// Buckets every object which can collide into all grid cells its bounding box touches,
+    /// boxes of grazing groups are grown by the graze radius and fast objects cover their path
+    fn fill_grid(&mut self, dt: f32) {
+        // Cells left empty by the last update are dropped, the others are reused
+        self.cells.retain(|_, cell| !cell.is_empty());
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        let cell_size = self.cell_size;
+        let cell_of = |x: f32, y: f32| ((x / cell_size).floor() as i32, (y / cell_size).floor() as i32);
+        for (id, object) in self.objects.iter_mut() {
+            if !self.matrix.is_active(object.data.group) {
+                continue;
+            }
+            let aabb = object.shape.as_ref().aabb(&object.isometry());
+            let margin = if self.matrix.is_grazing(object.data.group) {
+                self.graze_radius
+            } else {
+                0.0
+            };
+            object.mins = (aabb.mins().x - margin, aabb.mins().y - margin);
+            object.maxs = (aabb.maxs().x + margin, aabb.maxs().y + margin);
+            object.swept = (object.pos - object.prev).norm() > self.ccd_speed * dt;
+            if object.swept {
+                let prev = object.shape.as_ref().aabb(&object.prev_isometry());
+                object.mins = (object.mins.0.min(prev.mins().x - margin), object.mins.1.min(prev.mins().y - margin));
+                object.maxs = (object.maxs.0.max(prev.maxs().x + margin), object.maxs.1.max(prev.maxs().y + margin));
+            }
+            let (x0, y0) = cell_of(object.mins.0, object.mins.1);
+            let (x1, y1) = cell_of(object.maxs.0, object.maxs.1);
+            for x in x0..(x1 + 1) {
+                for y in y0..(y1 + 1) {
+                    self.cells.entry((x, y)).or_insert_with(Vec::new).push((object.data.group, *id));
+                }
+            }
+        }
+    }
+
+    /// Narrowphase test of a candidate pair, each pair is only tested in the cell
+    /// containing the lower corner of the overlap of both bounding boxes
+    fn test_pair(&self,
+                 cell: (i32, i32),
+                 a: usize,
+                 b: usize,
+                 grazing: &mut HashSet<(usize, usize)>,
+                 collisions: &mut Vec<Collision<PhysicsData>>) {
+        let (oa, ob) = match (self.objects.get(&a), self.objects.get(&b)) {
+            (Some(oa), Some(ob)) => (oa, ob),
+            _ => return,
+        };
+        if oa.maxs.0 < ob.mins.0 || ob.maxs.0 < oa.mins.0 || oa.maxs.1 < ob.mins.1 || ob.maxs.1 < oa.mins.1 {
+            return;
+        }
+        if self.cell_of(oa.mins.0.max(ob.mins.0), oa.mins.1.max(ob.mins.1)) != cell {
+            return;
+        }
+        let collides = self.matrix.collides(oa.data.group, ob.data.group);
+        let grazes = self.matrix.grazes(oa.data.group, ob.data.group);
+        let margin = if grazes { self.graze_radius } else { 0.5 };
+        let mut proximity = query::proximity(&oa.isometry(), oa.shape.as_ref(), &ob.isometry(), ob.shape.as_ref(), margin);
+        let mut toi = None;
+        if proximity != Proximity::Intersecting && collides && (oa.swept || ob.swept) {
+            toi = sweep_toi(oa, ob);
+            if toi.is_some() {
+                proximity = Proximity::Intersecting;
+            }
+        }
+        let report = match proximity {
+            Proximity::Intersecting => collides,
+            // Only the first update of a pass counts, a bullet hitting the player
+            // right after is still in the set and doesn't graze again
+            Proximity::WithinMargin => grazes && !self.grazing.contains(&(a, b)),
+            Proximity::Disjoint => false,
+        };
+        if grazes && proximity != Proximity::Disjoint {
+            grazing.insert((a, b));
+        }
+        if report {
+            collisions.push(Collision {
+                id1: oa.data.entity_id,
+                id2: ob.data.entity_id,
+                data1: oa.data.clone(),
+                data2: ob.data.clone(),
+                proximity: proximity,
+                contact: contact_between(oa, ob, toi, margin),
+            });
+        }
+    }
+}
+
+/// Closest hits first, ties (and overlaps) in entity order so results don't depend on map order
+fn sort_hits(hits: &mut Vec<QueryHit<PhysicsData>>) {
+    hits.sort_by(|a, b| {
+        match a.distance.partial_cmp(&b.distance) {
+            Some(Ordering::Equal) | None => (a.id, a.data.tag).cmp(&(b.id, b.data.tag)),
+            Some(o) => o,
+        }
+    });
+}
+
+/// Fraction of the update at which both objects, moving in a straight line from their
+/// previous positions, first touch
+fn sweep_toi(oa: &Object, ob: &Object) -> Option<f32> {
+    let (va, vb) = (oa.pos - oa.prev, ob.pos - ob.prev);
+    match query::time_of_impact(&oa.prev_isometry(), &va, oa.shape.as_ref(), &ob.prev_isometry(), &vb, ob.shape.as_ref()) {
+        Some(toi) if toi <= 1.0 => Some(toi),
+        _ => None,
+    }
+}
+
+/// Contact of both objects at their current positions, or where they were at the time of
+/// impact for swept hits
+fn contact_between(oa: &Object, ob: &Object, toi: Option<f32>, margin: f32) -> Contact {
+    let (pa, pb) = match toi {
+        Some(t) => (oa.prev + (oa.pos - oa.prev) * t, ob.prev + (ob.pos - ob.prev) * t),
+        None => (oa.pos, ob.pos),
+    };
+    let iso_a = Isometry2::new(pa, Vector1::new(oa.rot));
+    let iso_b = Isometry2::new(pb, Vector1::new(ob.rot));
+    match query::contact(&iso_a, oa.shape.as_ref(), &iso_b, ob.shape.as_ref(), margin) {
+        Some(c) => {
+            Contact {
+                point: Vector2::new((c.world1.x + c.world2.x) / 2.0, (c.world1.y + c.world2.y) / 2.0),
+                normal: c.normal,
+                depth: c.depth,
+            }
+        }
+        None => {
+            // Swept objects can end up just short of touching, fall back to their centers
+            let d = pb - pa;
+            Contact {
+                point: (pa + pb) * 0.5,
+                normal: if d.norm() > 0.0 { d.normalize() } else { Vector2::new(0.0, 1.0) },
+                depth: 0.0,
+            }
         }
     }
 }
 
 impl PhysicsEngine<Shape, PhysicsData> for DanmakuPhysics {
     fn add(&mut self, id: usize, pos: Vector2<f32>, shape: Shape, data: Rc<PhysicsData>) {
-        if data.group == 1 {
-            self.pid = id;
-        }
         self.objects.insert(id, Object {
             pos: pos,
+            prev: pos,
+            swept: false,
+            rot: 0.0,
             shape: shape,
             data: data,
+            mins: (pos.x, pos.y),
+            maxs: (pos.x, pos.y),
         });
     }
 
     fn remove(&mut self, id: &usize) {
         self.objects.remove(id);
+        self.grazing.retain(|&(a, b)| a != *id && b != *id);
     }
 
-    fn update(&mut self, _dt: f32) -> Vec<Collision<PhysicsData>> {
+    fn update(&mut self, dt: f32) -> Vec<Collision<PhysicsData>> {
+        self.fill_grid(dt);
         let mut collisions = Vec::new();
-        if let Some(player) = self.objects.get(&self.pid) {
-            let ps = player.shape.as_ref();
-            let ppos = player.pos;
-            let piso = Isometry2::new(ppos, na::zero());
-            for (id, object) in self.objects.iter() {
-                if *id != self.pid {
-                    let oshape = object.shape.as_ref();
-                    let oiso = Isometry2::new(object.pos, na::zero());
-                    if (object.pos.x - player.pos.x).abs() < 30.0 && (object.pos.y - player.pos.y).abs() < 30.0 {
-                        if query::proximity(&oiso, oshape, &piso, ps, 0.5) == Proximity::Intersecting {
-                            collisions.push(Collision {
-                                id1: player.data.entity_id,
-                                id2: object.data.entity_id,
-                                data1: player.data.clone(),
-                                data2: object.data.clone(),
-                            });
+        let mut grazing = util::hashset();
+        let mut runs = Vec::new();
+        let mut cells = Vec::new();
+        for (key, cell) in self.cells.iter_mut() {
+            if cell.len() > 1 {
+                // Sorting by group lets whole runs of non colliding groups be skipped at once
+                cell.sort();
+                cells.push(*key);
+            }
+        }
+        for key in cells {
+            let cell = &self.cells[&key];
+            runs.clear();
+            let mut start = 0;
+            for i in 1..(cell.len() + 1) {
+                if i == cell.len() || cell[i].0 != cell[start].0 {
+                    runs.push((start, i));
+                    start = i;
+                }
+            }
+            for (r, &(s1, e1)) in runs.iter().enumerate() {
+                for &(s2, e2) in runs[r..].iter() {
+                    let (g1, g2) = (cell[s1].0, cell[s2].0);
+                    if !self.matrix.collides(g1, g2) && !self.matrix.grazes(g1, g2) {
+                        continue;
+                    }
+                    for i in s1..e1 {
+                        let from = if s1 == s2 { i + 1 } else { s2 };
+                        for j in from..e2 {
+                            self.test_pair(key, cell[i].1, cell[j].1, &mut grazing, &mut collisions);
                         }
                     }
                 }
             }
         }
+        self.grazing = grazing;
+        for object in self.objects.values_mut() {
+            object.prev = object.pos;
+        }
         collisions
     }
 
@@ -82,6 +398,7 @@ impl PhysicsEngine<Shape, PhysicsData> for DanmakuPhysics {
             None => None
         }
     }
+
     fn set_pos(&mut self, id: &usize, pos: Vector2<f32>) {
         match self.objects.get_mut(id) {
             Some(obj) => {
@@ -90,4 +407,311 @@ impl PhysicsEngine<Shape, PhysicsData> for DanmakuPhysics {
             None => { }
         }
     }
+
+    fn set_positions(&mut self, moves: &[(usize, Vector2<f32>, f32)]) {
+        for &(id, pos, rot) in moves {
+            if let Some(obj) = self.objects.get_mut(&id) {
+                obj.pos = pos;
+                obj.rot = rot;
+            }
+        }
+    }
+
+    fn teleport(&mut self, id: &usize, pos: Vector2<f32>) {
+        if let Some(obj) = self.objects.get_mut(id) {
+            obj.pos = pos;
+            obj.prev = pos;
+        }
+    }
+
+    fn get_rot(&self, id: &usize) -> Option<f32> {
+        self.objects.get(id).map(|obj| obj.rot)
+    }
+
+    fn set_rot(&mut self, id: &usize, rot: f32) {
+        if let Some(obj) = self.objects.get_mut(id) {
+            obj.rot = rot;
+        }
+    }
+
+    fn set_shape(&mut self, id: &usize, shape: Shape) {
+        if let Some(obj) = self.objects.get_mut(id) {
+            obj.shape = shape;
+        }
+    }
+
+    fn raycast(&self, origin: Vector2<f32>, dir: Vector2<f32>, max_dist: f32, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        if dir.norm() == 0.0 {
+            return Vec::new();
+        }
+        let ray = Ray::new(Point2::new(origin.x, origin.y), dir.normalize());
+        let mut hits = Vec::new();
+        for obj in self.objects.values().filter(|obj| groups.contains(&obj.data.group)) {
+            let toi = obj.shape.as_ref().as_ray_cast().and_then(|r| r.toi_with_ray(&obj.isometry(), &ray, true));
+            match toi {
+                Some(toi) if toi <= max_dist => hits.push(obj.hit(toi)),
+                _ => { }
+            }
+        }
+        sort_hits(&mut hits);
+        hits
+    }
+
+    fn overlap_circle(&self, center: Vector2<f32>, radius: f32, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        let iso = Isometry2::new(center, Vector1::new(0.0));
+        let ball = Ball::new(radius);
+        let mut hits: Vec<_> = self.objects.values()
+            .filter(|obj| groups.contains(&obj.data.group))
+            .filter(|obj| query::proximity(&iso, &ball, &obj.isometry(), obj.shape.as_ref(), 0.0) == Proximity::Intersecting)
+            .map(|obj| obj.hit(0.0))
+            .collect();
+        sort_hits(&mut hits);
+        hits
+    }
+
+    fn overlap_rect(&self, center: Vector2<f32>, half_extents: Vector2<f32>, groups: &[u8]) -> Vec<QueryHit<PhysicsData>> {
+        let iso = Isometry2::new(center, Vector1::new(0.0));
+        let rect = Cuboid::new(half_extents);
+        let mut hits: Vec<_> = self.objects.values()
+            .filter(|obj| groups.contains(&obj.data.group))
+            .filter(|obj| query::proximity(&iso, &rect, &obj.isometry(), obj.shape.as_ref(), 0.0) == Proximity::Intersecting)
+            .map(|obj| obj.hit(0.0))
+            .collect();
+        sort_hits(&mut hits);
+        hits
+    }
+
+    fn nearest(&self, point: Vector2<f32>, group: u8) -> Option<QueryHit<PhysicsData>> {
+        let pt = Point2::new(point.x, point.y);
+        let mut best: Option<QueryHit<PhysicsData>> = None;
+        for obj in self.objects.values().filter(|obj| obj.data.group == group) {
+            let hit = match obj.shape.as_ref().as_point_query() {
+                Some(q) => obj.hit(q.distance_to_point(&obj.isometry(), &pt, true)),
+                None => continue,
+            };
+            let closer = match best {
+                Some(ref b) => (hit.distance, hit.id) < (b.distance, b.id),
+                None => true,
+            };
+            if closer {
+                best = Some(hit);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+fn add_ball(physics: &mut DanmakuPhysics, id: usize, pos: Vector2<f32>, radius: f32, group: u8) {
+    use engine::scene::Handle;
+
+    let handle = Handle { index: id, generation: 0 };
+    physics.add(id, pos, ShapeHandle2::new(Ball::new(radius)), Rc::new(PhysicsData::new(handle, 0, group)));
+}
+
+#[test]
+fn test_matrix_symmetry() {
+    let mut m = CollisionMatrix::new();
+    assert!(!m.is_active(3));
+    m.enable(3, 7);
+    assert!(m.collides(3, 7) && m.collides(7, 3));
+    assert!(m.is_active(3) && m.is_active(7));
+    m.disable(7, 3);
+    assert!(!m.collides(3, 7) && !m.collides(7, 3));
+    assert!(!m.is_active(3) && !m.is_active(7));
+
+    m.enable_graze(1, 2);
+    assert!(m.grazes(2, 1) && !m.collides(1, 2));
+    assert!(m.is_grazing(1) && m.is_grazing(2) && m.is_active(2));
+    m.disable_graze(2, 1);
+    assert!(!m.grazes(1, 2) && !m.is_grazing(1) && !m.is_active(1));
+}
+
+#[test]
+fn test_pair_reported_once_across_cells() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    physics.set_cell_size(4.0);
+    // Both overlap dozens of the same cells
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 10.0, PLAYER);
+    add_ball(&mut physics, 2, Vector2::new(5.0, 0.0), 10.0, ENEMY);
+    let collisions = physics.update(0.016);
+    assert_eq!(collisions.len(), 1);
+    assert_eq!((collisions[0].id1.index, collisions[0].id2.index), (1, 2));
+}
+
+#[test]
+fn test_empty_cells_pruned() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(8.0, 8.0), 1.0, ENEMY);
+    physics.update(0.016);
+    assert_eq!(physics.cells.len(), 1);
+    for i in 1..10 {
+        physics.set_pos(&1, Vector2::new(8.0 + 100.0 * i as f32, 8.0));
+        physics.update(0.016);
+    }
+    // Only the cell left empty this update is still around
+    assert_eq!(physics.cells.len(), 2);
+    physics.update(0.016);
+    assert_eq!(physics.cells.len(), 1);
+}
+
+#[test]
+fn test_graze_once_per_pass() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 2.0, PLAYER);
+    add_ball(&mut physics, 2, Vector2::new(0.0, 50.0), 2.0, ENEMY_BULLET);
+    // Out, in, staying in, out, in again
+    let path = [50.0, 10.0, 9.0, 8.0, 50.0, 10.0, 9.0];
+    let mut grazes = 0;
+    for y in path.iter() {
+        physics.set_pos(&2, Vector2::new(0.0, *y));
+        for c in physics.update(1.0) {
+            assert!(c.proximity == Proximity::WithinMargin);
+            grazes += 1;
+        }
+    }
+    assert_eq!(grazes, 2);
+}
+
+#[test]
+fn test_fast_bullet_does_not_tunnel() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 2.0, PLAYER);
+    add_ball(&mut physics, 2, Vector2::new(0.0, -50.0), 1.0, ENEMY_BULLET);
+    physics.update(0.016);
+    // Jumps clean over the player within one update
+    physics.set_pos(&2, Vector2::new(0.0, 50.0));
+    let hits = physics.update(0.016).into_iter().filter(|c| c.proximity == Proximity::Intersecting).count();
+    assert_eq!(hits, 1);
+
+    // Without sweeping the same jump back passes straight through
+    physics.set_ccd_speed(10000.0);
+    physics.set_pos(&2, Vector2::new(0.0, -50.0));
+    let hits = physics.update(0.016).into_iter().filter(|c| c.proximity == Proximity::Intersecting).count();
+    assert_eq!(hits, 0);
+}
+
+#[test]
+fn test_teleport_is_not_swept() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 2.0, PLAYER);
+    add_ball(&mut physics, 2, Vector2::new(0.0, -50.0), 1.0, ENEMY_BULLET);
+    physics.update(0.016);
+    physics.teleport(&2, Vector2::new(0.0, 50.0));
+    assert!(physics.update(0.016).iter().all(|c| c.proximity != Proximity::Intersecting));
+    // Moving on normally afterwards is swept from where it landed
+    physics.set_pos(&2, Vector2::new(0.0, 100.0));
+    assert!(physics.update(0.016).is_empty());
+}
+
+#[test]
+fn test_queries() {
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 30.0), 5.0, ENEMY);
+    add_ball(&mut physics, 2, Vector2::new(0.0, 60.0), 5.0, ENEMY);
+    add_ball(&mut physics, 3, Vector2::new(0.0, 45.0), 5.0, ITEM);
+    add_ball(&mut physics, 4, Vector2::new(30.0, 0.0), 5.0, ENEMY);
+    let ids = |hits: Vec<QueryHit<PhysicsData>>| hits.iter().map(|h| h.id.index).collect::<Vec<_>>();
+    let origin = Vector2::new(0.0, 0.0);
+    let up = Vector2::new(0.0, 2.0);
+
+    let hits = physics.raycast(origin, up, 100.0, &[ENEMY]);
+    assert!((hits[0].distance - 25.0).abs() < 0.001 && (hits[1].distance - 55.0).abs() < 0.001);
+    assert_eq!(ids(hits), vec![1, 2]);
+    assert_eq!(ids(physics.raycast(origin, up, 40.0, &[ENEMY])), vec![1]);
+    assert_eq!(ids(physics.raycast(origin, up, 100.0, &[ENEMY, ITEM])), vec![1, 3, 2]);
+    assert!(physics.raycast(origin, Vector2::new(0.0, 0.0), 100.0, &[ENEMY]).is_empty());
+
+    assert_eq!(ids(physics.overlap_circle(Vector2::new(0.0, 45.0), 20.0, &[ENEMY])), vec![1, 2]);
+    assert_eq!(ids(physics.overlap_circle(Vector2::new(0.0, 45.0), 20.0, &[ITEM])), vec![3]);
+    assert_eq!(ids(physics.overlap_rect(Vector2::new(15.0, 15.0), Vector2::new(20.0, 20.0), &[ENEMY])), vec![1, 4]);
+
+    let nearest = physics.nearest(Vector2::new(0.0, 40.0), ENEMY).unwrap();
+    assert_eq!(nearest.id.index, 1);
+    assert!((nearest.distance - 5.0).abs() < 0.001);
+    assert_eq!(physics.nearest(Vector2::new(0.0, 40.0), ITEM).unwrap().id.index, 3);
+    assert!(physics.nearest(Vector2::new(0.0, 40.0), PLAYER).is_none());
+}
+
+#[test]
+fn test_contact_between_circles() {
+    let close = |a: Vector2<f32>, b: Vector2<f32>| (a - b).norm() < 0.01;
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 5.0, PLAYER);
+    add_ball(&mut physics, 2, Vector2::new(8.0, 0.0), 5.0, ENEMY);
+    let collisions = physics.update(0.016);
+    assert_eq!(collisions.len(), 1);
+    let (c, contact) = (&collisions[0], collisions[0].contact);
+    assert_eq!((c.id1.index, c.id2.index), (1, 2));
+    assert!(close(contact.point, Vector2::new(4.0, 0.0)));
+    assert!(close(contact.normal, Vector2::new(1.0, 0.0)));
+    assert!((contact.depth - 2.0).abs() < 0.01);
+    // What the enemy is told
+    assert!(close(contact.flipped().normal, Vector2::new(-1.0, 0.0)));
+    assert!(close(contact.flipped().point, contact.point));
+
+    // Swept hits are reported where the objects met
+    physics.remove(&2);
+    add_ball(&mut physics, 3, Vector2::new(0.0, -50.0), 1.0, ENEMY_BULLET);
+    physics.update(0.016);
+    physics.set_pos(&3, Vector2::new(0.0, 50.0));
+    let collisions = physics.update(0.016);
+    let hit = collisions.iter().find(|c| c.proximity == Proximity::Intersecting).unwrap();
+    assert_eq!((hit.id1.index, hit.id2.index), (1, 3));
+    assert!(close(hit.contact.normal, Vector2::new(0.0, -1.0)));
+    assert!(hit.contact.point.y < -4.0 && hit.contact.point.y > -7.0);
+}
+
+#[test]
+fn test_swept_contact_fallback() {
+    use engine::scene::Handle;
+
+    let object = |index: usize, prev: Vector2<f32>, pos: Vector2<f32>| {
+        Object {
+            pos: pos,
+            prev: prev,
+            swept: true,
+            rot: 0.0,
+            shape: ShapeHandle2::new(Ball::new(1.0)),
+            data: Rc::new(PhysicsData::new(Handle { index: index, generation: 0 }, 0, ENEMY)),
+            mins: (pos.x, pos.y),
+            maxs: (pos.x, pos.y),
+        }
+    };
+    // Far apart at the time of impact, so there's no real contact to report
+    let a = object(1, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+    let b = object(2, Vector2::new(0.0, 10.0), Vector2::new(0.0, 1.0));
+    let contact = contact_between(&a, &b, Some(0.0), 0.5);
+    assert!((contact.point - Vector2::new(0.0, 5.0)).norm() < 0.01);
+    assert!((contact.normal - Vector2::new(0.0, 1.0)).norm() < 0.01);
+    assert_eq!(contact.depth, 0.0);
+}
+
+/// Run with `cargo test --release -- --ignored` to check the broadphase keeps up with a full screen of bullets
+#[test]
+#[ignore]
+fn bench_many_bullets() {
+    use clock_ticks;
+
+    const BULLETS: usize = 20000;
+    const UPDATES: usize = 60;
+    let mut physics = DanmakuPhysics::new(200.0);
+    add_ball(&mut physics, 1, Vector2::new(0.0, 0.0), 2.0, PLAYER);
+    let start_pos = |i: usize| Vector2::new((i % 200) as f32 * 2.0 - 200.0, (i / 200) as f32 * 4.0 - 200.0);
+    for i in 0..BULLETS {
+        add_ball(&mut physics, i + 2, start_pos(i), 3.0, ENEMY_BULLET);
+    }
+    let start = clock_ticks::precise_time_ms();
+    let mut hits = 0;
+    for step in 0..UPDATES {
+        for i in 0..BULLETS {
+            let pos = start_pos(i) + Vector2::new(0.0, -(step as f32));
+            physics.set_pos(&(i + 2), pos);
+        }
+        hits += physics.update(0.016).len();
+    }
+    let per_update = (clock_ticks::precise_time_ms() - start) as f32 / UPDATES as f32;
+    println!("{} bullets: {:?} ms per update, {} hits and grazes", BULLETS, per_update, hits);
+    assert!(hits > 0);
+    assert!(per_update < 16.0);
 }
@@ -0,0 +1,95 @@
+use nalgebra::Vector2;
+
+use engine::Engine;
+use engine::scene::Handle;
+use game::object::Object;
+use game::object::level::bullet::Bullet as BulletInfo;
+
+/// The current player entity, where it is, how many lives and bombs it has left and its items
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerState {
+    pub id: Handle,
+    pub pos: Vector2<f32>,
+    pub lives: usize,
+    pub bombs: usize,
+    pub power: usize,
+    pub life_pieces: usize,
+    pub bomb_pieces: usize,
+}
+
+impl Default for PlayerState {
+    fn default() -> PlayerState {
+        PlayerState {
+            id: Handle::default(),
+            pos: Vector2::new(0.0, 0.0),
+            lives: 0,
+            bombs: 0,
+            power: 0,
+            life_pieces: 0,
+            bomb_pieces: 0,
+        }
+    }
+}
+
+/// Amount of live enemies and enemy bullets
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Counts {
+    pub enemies: usize,
+    pub bullets: usize,
+}
+
+/// Bullets fired since the bullet manager last ran, as the bullet, its position and velocity
+#[derive(Clone, Debug, Default)]
+pub struct BulletQueue {
+    pub spawns: Vec<(BulletInfo, Vector2<f32>, Vector2<f32>)>,
+}
+
+impl BulletQueue {
+    pub fn push(&mut self, info: BulletInfo, pos: Vector2<f32>, vel: Vector2<f32>) {
+        self.spawns.push((info, pos, vel));
+    }
+
+    pub fn take(&mut self) -> Vec<(BulletInfo, Vector2<f32>, Vector2<f32>)> {
+        ::std::mem::replace(&mut self.spawns, Vec::new())
+    }
+}
+
+/// Sprites loaded along with the game's assets rather than by a level
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GameSprites {
+    /// Shared by all items
+    pub item: usize,
+}
+
+/// Running tally of the current play
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Score {
+    pub points: u64,
+    pub grazes: usize,
+    pub kills: usize,
+    pub captures: usize,
+    pub deaths: usize,
+    pub bombs_used: usize,
+}
+
+/// The boss currently fought, shown on the HUD
+#[derive(Clone, Debug, Default)]
+pub struct BossState {
+    pub active: bool,
+    pub health: usize,
+    pub max_health: usize,
+    /// Time left before the phase times out
+    pub time_left: f32,
+    /// Phases left after the current one
+    pub phases_left: usize,
+    pub spell: Option<String>,
+}
+
+/// Keeps the resources of a play in snapshots. `Counts` is left out, restored entities count themselves again
+pub fn keep_in_snapshots(engine: &Engine<Object>) {
+    let mut resources = engine.resources.borrow_mut();
+    resources.keep_in_snapshots::<PlayerState>();
+    resources.keep_in_snapshots::<BulletQueue>();
+    resources.keep_in_snapshots::<Score>();
+    resources.keep_in_snapshots::<BossState>();
+}
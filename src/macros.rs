@@ -13,7 +13,7 @@ macro_rules! impl_entity_enum {
                 }
             }
 
-            fn id(&self) -> usize {
+            fn id(&self) -> $crate::engine::scene::Handle {
                 match *self {
                     $(
                         $enum_name::$variant_name(ref val) => {
@@ -22,6 +22,16 @@ macro_rules! impl_entity_enum {
                     ),+
                 }
             }
+
+            fn snapshot(&self) -> Option<Box<Fn(&$crate::engine::Engine<$enum_name>) -> $enum_name>> {
+                match *self {
+                    $(
+                        $enum_name::$variant_name(ref val) => {
+                            val.snapshot()
+                        }
+                    ),+
+                }
+            }
 		}
 	);
 }
@@ -14,14 +14,38 @@ extern crate clock_ticks;
 mod macros;
 mod game;
 
+use std::env;
+
 use game::object::Object;
+use game::object::controller::Controller;
 use game::physics::DanmakuPhysics;
 use engine::Engine;
+use engine::replay::Replay;
+
+const REPLAY_PATH: &'static str = "last.replay";
 
 fn main() {
     let scaler = 200.0;
     let mut engine: Engine<Object> = Engine::new(scaler, 700, Box::new(DanmakuPhysics::new(scaler)));
     game::asset::load_assets(&mut engine);
-    engine.spawn_entity(game::object::controller::Controller::new(&engine));
+    game::resource::keep_in_snapshots(&engine);
+
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 2 && args[1] == "--replay" {
+        let replay = Replay::load(&args[2]).unwrap_or_else(|e| panic!("Failed to load replay: {}", e));
+        let controller = Controller::with_level(&engine, &replay.level, replay.start_time as i32)
+                             .unwrap_or_else(|e| panic!("Failed to load replay level: {}", e));
+        engine.play_replay(replay);
+        engine.spawn_entity(controller);
+    } else {
+        engine.spawn_entity(Controller::new(&engine));
+    }
     engine.run();
+
+    if let Some(replay) = engine.take_recording() {
+        match replay.save(REPLAY_PATH) {
+            Ok(()) => println!("Saved replay to {}", REPLAY_PATH),
+            Err(e) => println!("Failed to save replay: {}", e),
+        }
+    }
 }